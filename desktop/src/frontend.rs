@@ -11,24 +11,44 @@ use std::{
 use chrono::DateTime;
 use directories::UserDirs;
 use ds_emulator::{
-  apu::Sample,
+  apu::{interpolation::Interpolation, Sample},
   cpu::{
-    bus::{cartridge::BackupType, spi::SPI, touchscreen::SAMPLE_SIZE, Bus},
+    bus::{
+      cartridge::BackupType,
+      io_registers::{get_io_registers, IoCpu, IoRegister},
+      spi::SPI,
+      touchscreen::SAMPLE_SIZE,
+      Bus
+    },
     registers::{
       external_key_input_register::ExternalKeyInputRegister,
       key_input_register::KeyInputRegister
     }
   },
   gpu::{
+    engine_3d::{command_log::GxDump, texture_cache::DecodedTexture, upscaling::MAX_RESOLUTION_SCALE, Engine3d},
+    main_screen::Screen,
     registers::power_control_register1::PowerControlRegister1,
     GPU,
     SCREEN_HEIGHT,
     SCREEN_WIDTH
-  }, nds::Nds
+  },
+  nds::Nds,
+  settings::Settings,
+  video::{
+    filters::{
+      color_correction::ColorCorrection,
+      overlay::Overlay,
+      Upscaler,
+      VideoFilters,
+      MAX_UPSCALE_FACTOR
+    },
+    layout::{LayoutKind, Rotation, ScreenLayout}
+  }
 };
 
 use glow::RGBA;
-use imgui::{Context, Textures};
+use imgui::{Context, TextureId, Textures};
 use imgui_glow_renderer::{
   glow::{
     HasContext,
@@ -36,6 +56,7 @@ use imgui_glow_renderer::{
     PixelUnpackData,
     COLOR_ATTACHMENT0,
     COLOR_BUFFER_BIT,
+    LINEAR,
     NEAREST,
     READ_FRAMEBUFFER,
     RGBA8,
@@ -69,7 +90,20 @@ pub enum UIAction {
   Reset(bool),
   LoadGame(PathBuf),
   CreateSaveState,
-  LoadSaveState(PathBuf)
+  LoadSaveState(PathBuf),
+  DumpTextures,
+  WriteIoRegister(usize, u32, u32)
+}
+
+pub struct ViewerTexture {
+  pub hash: u64,
+  pub width: u32,
+  pub height: u32,
+  pub format: String,
+  pub pixels: Vec<u8>,
+  pub palette: Vec<u8>,
+  texture: NativeTexture,
+  texture_id: TextureId
 }
 
 pub struct DsAudioCallback {
@@ -143,6 +177,7 @@ pub struct Frontend {
   renderer: Renderer,
   gl: imgui_glow_renderer::glow::Context,
   texture: NativeTexture,
+  texture_size: (i32, i32),
   platform: SdlPlatform,
   show_menu: bool,
   imgui: imgui::Context,
@@ -159,7 +194,20 @@ pub struct Frontend {
   bios9_file: String,
   firmware: PathBuf,
   pub has_backup: bool,
-  pub save_entries: Vec<String>
+  pub save_entries: Vec<String>,
+  show_texture_viewer: bool,
+  pub viewer_textures: Vec<ViewerTexture>,
+  texture_pack: HashMap<u64, (u32, u32, Vec<u8>)>,
+  pub show_io_viewer: bool,
+  pub io_registers: Vec<IoRegister>,
+  io_values: Vec<u32>,
+  io_filter: String,
+  pub settings: Settings,
+  video_filters: VideoFilters,
+  screen_layout: ScreenLayout,
+  /// Follows the core's main screen hint in the hybrid and single screen layouts, until the screens get swapped by hand.
+  auto_main_screen: bool,
+  layout_size: (u32, u32)
 }
 
 impl Frontend {
@@ -183,6 +231,7 @@ impl Frontend {
     let window = video_subsystem
       .window("NDS Plus", SCREEN_WIDTH as u32 * 2, SCREEN_HEIGHT as u32 * 4)
       .opengl()
+      .resizable()
       .position_centered()
       .build()
       .unwrap();
@@ -204,12 +253,17 @@ impl Frontend {
       gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32);
       gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
 
-      gl.tex_storage_2d(
+      // large enough to hold both screens at the highest internal resolution, filters can make it grow further
+      gl.tex_image_2d(
         TEXTURE_2D,
-        1,
-        RGBA8,
-        SCREEN_WIDTH as i32 * 2,
-        SCREEN_HEIGHT as i32 * 4
+        0,
+        RGBA8 as i32,
+        SCREEN_WIDTH as i32 * MAX_RESOLUTION_SCALE as i32,
+        SCREEN_HEIGHT as i32 * 2 * MAX_RESOLUTION_SCALE as i32,
+        0,
+        RGBA,
+        UNSIGNED_BYTE,
+        None
       );
 
       gl.bind_framebuffer(READ_FRAMEBUFFER, Some(framebuffer));
@@ -340,6 +394,7 @@ impl Frontend {
       renderer,
       gl,
       texture,
+      texture_size: (SCREEN_WIDTH as i32 * MAX_RESOLUTION_SCALE as i32, SCREEN_HEIGHT as i32 * 2 * MAX_RESOLUTION_SCALE as i32),
       platform,
       imgui,
       textures,
@@ -354,7 +409,19 @@ impl Frontend {
       bios9_file,
       has_backup: false,
       firmware,
-      save_entries
+      save_entries,
+      show_texture_viewer: false,
+      viewer_textures: Vec::new(),
+      texture_pack: HashMap::new(),
+      show_io_viewer: false,
+      settings: Settings::new(),
+      video_filters: VideoFilters::new(),
+      screen_layout: ScreenLayout::new(),
+      auto_main_screen: true,
+      layout_size: (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32 * 2),
+      io_registers: get_io_registers(),
+      io_values: Vec::new(),
+      io_filter: String::new()
     }
   }
 
@@ -402,12 +469,18 @@ impl Frontend {
     if !self.use_control_stick {
       let state = self.event_pump.mouse_state();
 
-      let y = state.y();
-      let x = state.x();
+      // mouse coordinates are in window points while the screens are laid out in drawable pixels, which differ on HiDPI displays
+      let (window_width, window_height) = self.window.size();
+      let (drawable_width, drawable_height) = self.window.drawable_size();
 
-      if state.left() && y >= SCREEN_HEIGHT as i32 * 2 && x >= 0 {
+      let x = (state.x() as i64 * drawable_width as i64 / window_width.max(1) as i64) as i32;
+      let y = (state.y() as i64 * drawable_height as i64 / window_height.max(1) as i64) as i32;
+
+      let touch = self.screen_layout.map_touch(drawable_width, drawable_height, x, y);
+
+      if let (true, Some((x, y))) = (state.left(), touch) {
         bus.arm7.extkeyin.remove(ExternalKeyInputRegister::PEN_DOWN);
-        bus.touchscreen.touch_screen(x as u16 / 2, y as u16 / 2 - SCREEN_HEIGHT);
+        bus.touchscreen.touch_screen(x, y);
       } else if !state.left() {
         bus.touchscreen.release_screen();
         bus.arm7.extkeyin.insert(ExternalKeyInputRegister::PEN_DOWN);
@@ -486,6 +559,45 @@ impl Frontend {
 
   }
 
+  pub fn save_gx_dump(rom_path: String, dump: GxDump) {
+    if let Some(user_dirs) = UserDirs::new() {
+      let document_path = user_dirs.document_dir().unwrap();
+
+      let delimiter = if std::env::consts::OS == "windows" {
+        "\\"
+      } else {
+        "/"
+      };
+
+      let folder_path = Path::new(
+        &format!(
+          "{}{delimiter}NDS-Plus{delimiter}gx_dumps{delimiter}{}",
+          document_path.to_str().unwrap(),
+          rom_path.split(delimiter).last().unwrap()
+        )
+      ).with_extension("");
+
+      fs::create_dir_all(&folder_path).unwrap();
+
+      let current_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("an error occurred")
+        .as_millis();
+
+      let file_path = format!("{}{delimiter}frame_{}.gxdump", folder_path.to_str().unwrap(), current_time);
+
+      let buf = dump.to_bytes();
+
+      std::thread::spawn(move || {
+        let compressed = zstd::encode_all(&*buf, 9).unwrap();
+
+        fs::write(file_path, compressed).unwrap();
+
+        println!("finished writing gx dump");
+      });
+    }
+  }
+
   pub fn load_save_state(
     nds: &mut Nds,
     bios7_file: String,
@@ -648,6 +760,9 @@ impl Frontend {
               self.has_backup,
               state_path
             );
+          } else if keycode.unwrap() == Keycode::F9 && self.rom_loaded {
+            let ref mut bus = *nds.bus.borrow_mut();
+            bus.gpu.engine3d.start_command_log();
           } else if keycode.unwrap() == Keycode::H {
             if !nds.stepping {
               nds.stepping = true;
@@ -658,6 +773,9 @@ impl Frontend {
             }
           } else if keycode.unwrap() == Keycode::Y {
             nds.paused = !nds.paused;
+          } else if keycode.unwrap() == Keycode::F4 {
+            self.screen_layout.swap_screens = !self.screen_layout.swap_screens;
+            self.auto_main_screen = false;
           }
         }
         Event::KeyUp { keycode, .. } => {
@@ -770,6 +888,56 @@ impl Frontend {
       (&gpu.engine_b.pixels, &gpu.engine_a.pixels)
     };
 
+    let scale = gpu.resolution_scale();
+
+    let (mut width, mut height) = (SCREEN_WIDTH as usize * scale, SCREEN_HEIGHT as usize * scale);
+
+    let filters_enabled = self.video_filters.is_enabled();
+
+    if filters_enabled {
+      (width, height) = self.video_filters.apply_to_screens(gpu);
+    }
+
+    if self.auto_main_screen && matches!(self.screen_layout.kind, LayoutKind::Hybrid | LayoutKind::Single) {
+      self.screen_layout.swap_screens = gpu.main_screen_hint.screen == Screen::Bottom;
+    }
+
+    let rotated = self.screen_layout.rotation != Rotation::None;
+
+    let (texture_width, texture_height) = if matches!(self.screen_layout.rotation, Rotation::Rotate90 | Rotation::Rotate270) {
+      (height, width)
+    } else {
+      (width, height)
+    };
+
+    self.resize_texture(texture_width as i32, 2 * texture_height as i32);
+    self.resize_window();
+
+    let (top, bottom) = if filters_enabled {
+      (self.video_filters.output(0), self.video_filters.output(1))
+    } else {
+      (&top[..], &bottom[..])
+    };
+
+    let rotated_images;
+
+    let (top, bottom) = if rotated {
+      rotated_images = [
+        self.screen_layout.rotate_image(top, width, height).0,
+        self.screen_layout.rotate_image(bottom, width, height).0
+      ];
+
+      (&rotated_images[0][..], &rotated_images[1][..])
+    } else {
+      (top, bottom)
+    };
+
+    let (width, height) = (texture_width as i32, texture_height as i32);
+
+    let (window_width, window_height) = self.window.drawable_size();
+
+    let rects = self.screen_layout.screen_rects(window_width, window_height);
+
     unsafe {
       self.gl.clear(glow::COLOR_BUFFER_BIT);
       self.gl.bind_texture(TEXTURE_2D, Some(self.texture));
@@ -779,8 +947,8 @@ impl Frontend {
         0,
         0,
         0,
-        SCREEN_WIDTH as i32,
-        SCREEN_HEIGHT as i32,
+        width,
+        height,
         RGBA,
         UNSIGNED_BYTE,
         PixelUnpackData::Slice(&top)
@@ -790,29 +958,280 @@ impl Frontend {
         TEXTURE_2D,
         0,
         0,
-        SCREEN_HEIGHT as i32,
-        SCREEN_WIDTH as i32,
-        SCREEN_HEIGHT as i32,
+        height,
+        width,
+        height,
         RGBA,
         UNSIGNED_BYTE,
         PixelUnpackData::Slice(&bottom)
       );
 
-      self.gl.blit_framebuffer(
-        0,
-        SCREEN_HEIGHT as i32 * 2,
-        SCREEN_WIDTH as i32,
-        0,
+      for (screen, rect) in rects.iter().enumerate() {
+        let Some(rect) = rect else {
+          continue;
+        };
+
+        let (x, y) = (rect.x.round() as i32, rect.y.round() as i32);
+        let (rect_width, rect_height) = (rect.width.round() as i32, rect.height.round() as i32);
+
+        // the texture is stored upside down compared to the window
+        let source_y = screen as i32 * height;
+        let window_y = window_height as i32 - y - rect_height;
+
+        self.gl.blit_framebuffer(
+          0,
+          source_y + height,
+          width,
+          source_y,
+          x,
+          window_y,
+          x + rect_width,
+          window_y + rect_height,
+          COLOR_BUFFER_BIT,
+          if width > rect_width { LINEAR } else { NEAREST }
+        );
+      }
+    }
+
+    gpu.engine3d.capture_textures = self.show_texture_viewer;
+
+    if self.show_texture_viewer {
+      self.update_viewer_textures(&gpu.engine3d.frame_textures);
+    } else if !self.viewer_textures.is_empty() {
+      self.update_viewer_textures(&[]);
+    }
+  }
+
+  /// Resizes the window to show the layout at 2x whenever its shape changes.
+  fn resize_window(&mut self) {
+    let layout_size = self.screen_layout.size();
+
+    if layout_size != self.layout_size {
+      self.layout_size = layout_size;
+
+      let _ = self.window.set_size(layout_size.0 * 2, layout_size.1 * 2);
+    }
+  }
+
+  /// Makes the screen texture at least as large as the given size.
+  fn resize_texture(&mut self, width: i32, height: i32) {
+    if width <= self.texture_size.0 && height <= self.texture_size.1 {
+      return;
+    }
+
+    self.texture_size = (width.max(self.texture_size.0), height.max(self.texture_size.1));
+
+    unsafe {
+      self.gl.bind_texture(TEXTURE_2D, Some(self.texture));
+
+      self.gl.tex_image_2d(
+        TEXTURE_2D,
         0,
+        RGBA8 as i32,
+        self.texture_size.0,
+        self.texture_size.1,
         0,
-        SCREEN_WIDTH as i32 * 2,
-        SCREEN_HEIGHT as i32 * 4,
-        COLOR_BUFFER_BIT,
-        NEAREST
+        RGBA,
+        UNSIGNED_BYTE,
+        None
       );
     }
   }
 
+  /// Textures with the same hash decode to the same image, so only the ones that weren't
+  /// shown last frame get uploaded, and the ones no longer used get deleted.
+  fn update_viewer_textures(&mut self, frame_textures: &[DecodedTexture]) {
+    let mut previous: HashMap<u64, ViewerTexture> = self
+      .viewer_textures
+      .drain(..)
+      .map(|viewer_texture| (viewer_texture.hash, viewer_texture))
+      .collect();
+
+    for decoded in frame_textures {
+      if self.viewer_textures.iter().any(|viewer_texture| viewer_texture.hash == decoded.hash) {
+        continue;
+      }
+
+      if let Some(viewer_texture) = previous.remove(&decoded.hash) {
+        self.viewer_textures.push(viewer_texture);
+
+        continue;
+      }
+
+      let texture = unsafe { self.gl.create_texture().unwrap() };
+
+      unsafe {
+        self.gl.bind_texture(TEXTURE_2D, Some(texture));
+
+        self.gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32);
+        self.gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
+
+        self.gl.tex_image_2d(
+          TEXTURE_2D,
+          0,
+          RGBA8 as i32,
+          decoded.width as i32,
+          decoded.height as i32,
+          0,
+          RGBA,
+          UNSIGNED_BYTE,
+          Some(&decoded.pixels)
+        );
+      }
+
+      let texture_id = self.textures.insert(texture);
+
+      self.viewer_textures.push(ViewerTexture {
+        hash: decoded.hash,
+        width: decoded.width,
+        height: decoded.height,
+        format: format!("{:?}", decoded.format),
+        pixels: decoded.pixels.clone(),
+        palette: decoded.palette.clone(),
+        texture,
+        texture_id
+      });
+    }
+
+    for viewer_texture in previous.into_values() {
+      self.textures.remove(viewer_texture.texture_id);
+
+      unsafe { self.gl.delete_texture(viewer_texture.texture) };
+    }
+  }
+
+  /// Loads the replacement textures in `Documents/NDS-Plus/textures/packs/<game code>`.
+  /// Each png (in any subfolder) is named after the hash of the texture it replaces,
+  /// which is the same name the texture dumper gives it.
+  pub fn load_texture_pack(&mut self, game_code: u32) {
+    self.texture_pack.clear();
+
+    if let Some(user_dirs) = UserDirs::new() {
+      let game_code = String::from_utf8_lossy(&game_code.to_le_bytes()).to_string();
+
+      let pack_path = user_dirs
+        .document_dir()
+        .unwrap()
+        .join("NDS-Plus")
+        .join("textures")
+        .join("packs")
+        .join(game_code);
+
+      let mut folders = vec![pack_path];
+
+      while let Some(folder) = folders.pop() {
+        let entries = match fs::read_dir(&folder) {
+          Ok(entries) => entries,
+          Err(_) => continue
+        };
+
+        for entry in entries.flatten() {
+          let path = entry.path();
+
+          if path.is_dir() {
+            folders.push(path);
+            continue;
+          }
+
+          if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+            let hash = path
+              .file_stem()
+              .and_then(|stem| stem.to_str())
+              .and_then(|stem| u64::from_str_radix(stem, 16).ok());
+
+            if let (Some(hash), Some(image)) = (hash, Self::read_png(&path)) {
+              self.texture_pack.insert(hash, image);
+            }
+          }
+        }
+      }
+
+      if !self.texture_pack.is_empty() {
+        println!("loaded {} replacement textures", self.texture_pack.len());
+      }
+    }
+  }
+
+  /// Replacements aren't part of the emulator state, so this needs to be called again after a reset or save state load.
+  pub fn apply_texture_pack(&self, engine3d: &mut Engine3d) {
+    engine3d.clear_texture_replacements();
+
+    for (hash, (width, height, pixels)) in &self.texture_pack {
+      engine3d.add_texture_replacement(*hash, *width, *height, pixels);
+    }
+  }
+
+  fn read_png(path: &Path) -> Option<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(fs::File::open(path).ok()?);
+
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().ok()?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+
+    let info = reader.next_frame(&mut buf).ok()?;
+
+    let bytes = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+      png::ColorType::Rgba => bytes.to_vec(),
+      png::ColorType::Rgb => bytes.chunks(3).flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 0xff]).collect(),
+      png::ColorType::GrayscaleAlpha => bytes.chunks(2).flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+      png::ColorType::Grayscale => bytes.iter().flat_map(|pixel| [*pixel, *pixel, *pixel, 0xff]).collect(),
+      png::ColorType::Indexed => return None
+    };
+
+    Some((info.width, info.height, pixels))
+  }
+
+  pub fn update_io_values(&mut self, bus: &mut Bus) {
+    self.io_values = self.io_registers.iter().map(|register| bus.read_io_register(register)).collect();
+  }
+
+  pub fn dump_textures(&self, game_code: u32) {
+    if let Some(user_dirs) = UserDirs::new() {
+      let document_path = user_dirs.document_dir().unwrap();
+
+      let delimiter = if std::env::consts::OS == "windows" {
+        "\\"
+      } else {
+        "/"
+      };
+
+      let game_code = String::from_utf8_lossy(&game_code.to_le_bytes()).to_string();
+
+      let folder_path = format!(
+        "{}{delimiter}NDS-Plus{delimiter}textures{delimiter}dumps{delimiter}{}",
+        document_path.to_str().unwrap(),
+        game_code
+      );
+
+      fs::create_dir_all(&folder_path).unwrap();
+
+      for texture in &self.viewer_textures {
+        let file_path = format!("{folder_path}{delimiter}{:016x}.png", texture.hash);
+
+        Self::write_png(Path::new(&file_path), texture.width, texture.height, &texture.pixels);
+      }
+
+      println!("dumped {} textures to {folder_path}", self.viewer_textures.len());
+    }
+  }
+
+  fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) {
+    let file = fs::File::create(path).unwrap();
+
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().unwrap();
+
+    writer.write_image_data(pixels).unwrap();
+  }
+
 
   pub fn end_frame(&mut self) {
     self.window.gl_swap_window();
@@ -960,11 +1379,261 @@ impl Frontend {
             cloud_service.logout();
           }
 
+          menu.end();
+        }
+        if let Some(menu) = ui.begin_menu("Video") {
+          if ui.menu_item_config("Unlimited polygons").selected(self.settings.unlimited_polygons).build() {
+            self.settings.unlimited_polygons = !self.settings.unlimited_polygons;
+          }
+          if ui.menu_item_config("Threaded 3D rendering").selected(self.settings.threaded_rendering).build() {
+            self.settings.threaded_rendering = !self.settings.threaded_rendering;
+          }
+          if ui.menu_item_config("Scanline accurate 3D").selected(self.settings.scanline_rendering).build() {
+            self.settings.scanline_rendering = !self.settings.scanline_rendering;
+          }
+          if let Some(menu) = ui.begin_menu("Internal resolution") {
+            for scale in 1..=MAX_RESOLUTION_SCALE {
+              if ui.menu_item_config(format!("{scale}x")).selected(self.settings.resolution_scale == scale).build() {
+                self.settings.resolution_scale = scale;
+              }
+            }
+
+            menu.end();
+          }
+          if let Some(menu) = ui.begin_menu("Filters") {
+            for (name, upscaler) in [("No upscaling", Upscaler::None), ("Scale2x", Upscaler::Scale2x), ("HQx", Upscaler::Hqx), ("xBRZ", Upscaler::Xbrz)] {
+              if ui.menu_item_config(name).selected(self.video_filters.upscaler == upscaler).build() {
+                self.video_filters.upscaler = upscaler;
+              }
+            }
+
+            ui.separator();
+
+            for factor in 2..=MAX_UPSCALE_FACTOR {
+              if ui.menu_item_config(format!("Upscale {factor}x")).selected(self.video_filters.upscale_factor == factor).build() {
+                self.video_filters.upscale_factor = factor;
+              }
+            }
+
+            ui.separator();
+
+            for (name, overlay) in [("No overlay", Overlay::None), ("LCD grid", Overlay::LcdGrid), ("Scanlines", Overlay::Scanlines)] {
+              if ui.menu_item_config(name).selected(self.video_filters.overlay == overlay).build() {
+                self.video_filters.overlay = overlay;
+              }
+            }
+
+            ui.separator();
+
+            for (name, color_correction) in [("No color correction", ColorCorrection::None), ("LCD gamma", ColorCorrection::Gamma), ("LCD colors", ColorCorrection::Lcd)] {
+              if ui.menu_item_config(name).selected(self.video_filters.color_correction == color_correction).build() {
+                self.video_filters.color_correction = color_correction;
+              }
+            }
+
+            ui.separator();
+
+            if ui.menu_item_config("Ghosting").selected(self.video_filters.ghosting).build() {
+              self.video_filters.ghosting = !self.video_filters.ghosting;
+            }
+
+            menu.end();
+          }
+          if let Some(menu) = ui.begin_menu("Screen layout") {
+            for (name, kind) in [("Vertical", LayoutKind::Vertical), ("Horizontal", LayoutKind::Horizontal), ("Hybrid", LayoutKind::Hybrid), ("Single screen", LayoutKind::Single)] {
+              if ui.menu_item_config(name).selected(self.screen_layout.kind == kind).build() {
+                self.screen_layout.kind = kind;
+              }
+            }
+
+            ui.separator();
+
+            for (name, rotation) in [("No rotation", Rotation::None), ("Rotate 90\u{b0}", Rotation::Rotate90), ("Rotate 180\u{b0}", Rotation::Rotate180), ("Rotate 270\u{b0}", Rotation::Rotate270)] {
+              if ui.menu_item_config(name).selected(self.screen_layout.rotation == rotation).build() {
+                self.screen_layout.rotation = rotation;
+              }
+            }
+
+            ui.separator();
+
+            for gap in [0, 16, 32, 64] {
+              let name = if gap == 0 { "No gap".to_string() } else { format!("{gap}px gap") };
+
+              if ui.menu_item_config(name).selected(self.screen_layout.gap == gap).build() {
+                self.screen_layout.gap = gap;
+              }
+            }
+
+            ui.separator();
+
+            if ui.menu_item_config("Swap screens").shortcut("F4").selected(self.screen_layout.swap_screens).build() {
+              self.screen_layout.swap_screens = !self.screen_layout.swap_screens;
+              self.auto_main_screen = false;
+            }
+            if ui.menu_item_config("Automatic main screen").selected(self.auto_main_screen).build() {
+              self.auto_main_screen = !self.auto_main_screen;
+            }
+            if ui.menu_item_config("Integer scaling").selected(self.screen_layout.integer_scale).build() {
+              self.screen_layout.integer_scale = !self.screen_layout.integer_scale;
+            }
+
+            menu.end();
+          }
+
+          menu.end();
+        }
+        if let Some(menu) = ui.begin_menu("Audio") {
+          if let Some(menu) = ui.begin_menu("Interpolation") {
+            for (name, interpolation) in [
+              ("None", Interpolation::None),
+              ("Linear", Interpolation::Linear),
+              ("Cosine", Interpolation::Cosine),
+              ("Cubic", Interpolation::Cubic),
+              ("Gaussian", Interpolation::Gaussian)
+            ] {
+              if ui.menu_item_config(name).selected(self.settings.interpolation == interpolation).build() {
+                self.settings.interpolation = interpolation;
+              }
+            }
+
+            menu.end();
+          }
+
+          menu.end();
+        }
+        if let Some(menu) = ui.begin_menu("Debug") {
+          if ui.menu_item_config("Texture viewer").selected(self.show_texture_viewer).build() {
+            self.show_texture_viewer = !self.show_texture_viewer;
+          }
+          if ui.menu_item_config("I/O registers").selected(self.show_io_viewer).build() {
+            self.show_io_viewer = !self.show_io_viewer;
+          }
+
           menu.end();
         }
       });
     }
 
+    if self.show_texture_viewer {
+      ui.window("Texture viewer")
+        .size([480.0, 640.0], imgui::Condition::FirstUseEver)
+        .opened(&mut self.show_texture_viewer)
+        .build(|| {
+          ui.text(format!("{} textures used this frame", self.viewer_textures.len()));
+
+          if self.rom_loaded && ui.button("Dump to PNG") {
+            action = UIAction::DumpTextures;
+          }
+
+          ui.separator();
+
+          for texture in &self.viewer_textures {
+            ui.text(format!("{:016x}: {}x{} {}", texture.hash, texture.width, texture.height, texture.format));
+
+            let scale = if texture.width <= 64 && texture.height <= 64 { 2.0 } else { 1.0 };
+
+            imgui::Image::new(texture.texture_id, [texture.width as f32 * scale, texture.height as f32 * scale]).build(ui);
+
+            if !texture.palette.is_empty() {
+              let draw_list = ui.get_window_draw_list();
+
+              let [x, y] = ui.cursor_screen_pos();
+
+              for (i, entry) in texture.palette.chunks(4).enumerate() {
+                let column = (i % 32) as f32;
+                let row = (i / 32) as f32;
+
+                let start = [x + column * 8.0, y + row * 8.0];
+                let end = [start[0] + 8.0, start[1] + 8.0];
+
+                draw_list
+                  .add_rect(start, end, [entry[0] as f32 / 255.0, entry[1] as f32 / 255.0, entry[2] as f32 / 255.0, 1.0])
+                  .filled(true)
+                  .build();
+              }
+
+              let rows = (texture.palette.len() / 4 + 31) / 32;
+
+              ui.dummy([256.0, rows as f32 * 8.0]);
+            }
+
+            ui.separator();
+          }
+        });
+    }
+
+    if self.show_io_viewer {
+      ui.window("I/O registers")
+        .size([420.0, 640.0], imgui::Condition::FirstUseEver)
+        .opened(&mut self.show_io_viewer)
+        .build(|| {
+          ui.input_text("Filter", &mut self.io_filter).build();
+
+          ui.separator();
+
+          let filter = self.io_filter.to_uppercase();
+
+          for (i, (register, value)) in self.io_registers.iter().zip(self.io_values.iter()).enumerate() {
+            let cpu = match register.cpu {
+              IoCpu::Arm9 => "ARM9",
+              IoCpu::Arm7 => "ARM7"
+            };
+
+            let label = format!(
+              "{:08X} {cpu} {}: {:0width$X}",
+              register.address,
+              register.name,
+              value,
+              width = (register.size * 2) as usize
+            );
+
+            if !filter.is_empty() && !label.to_uppercase().contains(&filter) {
+              continue;
+            }
+
+            if !ui.collapsing_header(format!("{label}###io{i}"), imgui::TreeNodeFlags::empty()) {
+              continue;
+            }
+
+            let mut raw = *value;
+
+            if ui
+              .input_scalar(format!("Raw###raw{i}"), &mut raw)
+              .display_format("%X")
+              .chars_hexadecimal(true)
+              .enter_returns_true(true)
+              .read_only(register.read_only)
+              .build()
+            {
+              action = UIAction::WriteIoRegister(i, raw, raw ^ *value);
+            }
+
+            for (j, field) in register.fields.iter().enumerate() {
+              let field_value = field.get(*value);
+
+              if field.length == 1 {
+                let mut checked = field_value != 0;
+
+                if ui.checkbox(format!("{}###field{i}_{j}", field.name), &mut checked) && !register.read_only {
+                  action = UIAction::WriteIoRegister(i, field.set(*value, checked as u32), field.mask());
+                }
+              } else {
+                let mut new_value = field_value;
+
+                if ui
+                  .input_scalar(format!("{}###field{i}_{j}", field.name), &mut new_value)
+                  .enter_returns_true(true)
+                  .read_only(register.read_only)
+                  .build()
+                {
+                  action = UIAction::WriteIoRegister(i, field.set(*value, new_value), field.mask());
+                }
+              }
+            }
+          }
+        });
+    }
+
     let draw_data = self.imgui.render();
 
     self.renderer.render(&self.gl, &mut self.textures, draw_data).unwrap();
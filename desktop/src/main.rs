@@ -76,6 +76,13 @@ fn handle_frontend(
       nds.reset(&rom);
       detect_backup_type(frontend, nds, rom_path_str.clone(), None);
 
+      {
+        let ref mut bus = *nds.bus.borrow_mut();
+
+        frontend.load_texture_pack(bus.cartridge.header.game_code);
+        frontend.apply_texture_pack(&mut bus.gpu.engine3d);
+      }
+
       *has_backup = {
         match &nds.bus.borrow().cartridge.backup {
           BackupType::Eeprom(_) | BackupType::Flash(_) => true,
@@ -143,6 +150,8 @@ fn handle_frontend(
 
       detect_backup_type(frontend, nds, rom_path_str.clone(), bytes);
 
+      frontend.apply_texture_pack(&mut nds.bus.borrow_mut().gpu.engine3d);
+
       *has_backup = {
         match &nds.bus.borrow().cartridge.backup {
           BackupType::Eeprom(_) | BackupType::Flash(_) => true,
@@ -164,6 +173,14 @@ fn handle_frontend(
         false
       );
     }
+    UIAction::WriteIoRegister(index, value, edited_mask) => {
+      nds.bus.borrow_mut().write_io_register(&frontend.io_registers[index], value, edited_mask);
+    }
+    UIAction::DumpTextures => {
+      let game_code = nds.bus.borrow().cartridge.header.game_code;
+
+      frontend.dump_textures(game_code);
+    }
     UIAction::LoadSaveState(state_path) => {
       Frontend::load_save_state(
         nds,
@@ -178,6 +195,8 @@ fn handle_frontend(
         state_path
       );
 
+      frontend.apply_texture_pack(&mut nds.bus.borrow_mut().gpu.engine3d);
+
       return true;
     }
   }
@@ -319,6 +338,11 @@ fn main() {
     frontend.rom_loaded = true;
 
     detect_backup_type(&mut frontend, &mut nds, rom_path.clone(), None);
+
+    let ref mut bus = *nds.bus.borrow_mut();
+
+    frontend.load_texture_pack(bus.cartridge.header.game_code);
+    frontend.apply_texture_pack(&mut bus.gpu.engine3d);
   }
 
   let mut frame_finished = false;
@@ -372,8 +396,18 @@ fn main() {
         frame_finished = false;
 
         frontend.render(&mut bus.gpu);
+
+        if let Some(dump) = bus.gpu.engine3d.take_gx_dump() {
+          Frontend::save_gx_dump(rom_path.clone(), dump);
+        }
+
+        if frontend.show_io_viewer {
+          frontend.update_io_values(bus);
+        }
       }
 
+      nds.apply_settings(&frontend.settings);
+
       frontend.resume_mic();
 
       if handle_frontend(
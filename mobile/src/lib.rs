@@ -1,10 +1,11 @@
 use std::{collections::VecDeque, sync::{Arc, Mutex}};
 
 use ds_emulator::{
-  apu::Sample, cpu::{bus::{backup_file::BackupFile, cartridge::BackupType, spi::SPI, touchscreen::SAMPLE_SIZE}, registers::{
+  apu::{interpolation::Interpolation, Sample}, cpu::{bus::{backup_file::BackupFile, cartridge::BackupType, spi::SPI, touchscreen::SAMPLE_SIZE}, registers::{
     external_key_input_register::ExternalKeyInputRegister,
     key_input_register::KeyInputRegister
-  }}, gpu::registers::power_control_register1::PowerControlRegister1, nds::Nds
+  }}, gpu::{main_screen::Screen, registers::power_control_register1::PowerControlRegister1}, nds::Nds, settings::Settings,
+  video::filters::{color_correction::ColorCorrection, overlay::Overlay, Upscaler, VideoFilters}
 };
 
 extern crate ds_emulator;
@@ -89,6 +90,54 @@ mod ffi {
     #[swift_bridge(swift_name="setPause")]
     fn set_pause(&mut self, val: bool);
 
+    #[swift_bridge(swift_name="setUnlimitedPolygons")]
+    fn set_unlimited_polygons(&mut self, val: bool);
+
+    #[swift_bridge(swift_name="setThreadedRendering")]
+    fn set_threaded_rendering(&mut self, val: bool);
+
+    #[swift_bridge(swift_name="setScanlineRendering")]
+    fn set_scanline_rendering(&mut self, val: bool);
+
+    #[swift_bridge(swift_name="setInterpolation")]
+    fn set_interpolation(&mut self, val: u8);
+
+    #[swift_bridge(swift_name="setUpscaler")]
+    fn set_upscaler(&mut self, val: u8);
+
+    #[swift_bridge(swift_name="setUpscaleFactor")]
+    fn set_upscale_factor(&mut self, val: usize);
+
+    #[swift_bridge(swift_name="setOverlay")]
+    fn set_overlay(&mut self, val: u8);
+
+    #[swift_bridge(swift_name="setColorCorrection")]
+    fn set_color_correction(&mut self, val: u8);
+
+    #[swift_bridge(swift_name="setGhosting")]
+    fn set_ghosting(&mut self, val: bool);
+
+    #[swift_bridge(swift_name="filtersEnabled")]
+    fn filters_enabled(&self) -> bool;
+
+    #[swift_bridge(swift_name="getFilteredPicturePointer")]
+    fn get_filtered_picture_pointer(&self, screen: usize) -> *const u8;
+
+    #[swift_bridge(swift_name="filteredWidth")]
+    fn filtered_width(&self) -> usize;
+
+    #[swift_bridge(swift_name="filteredHeight")]
+    fn filtered_height(&self) -> usize;
+
+    #[swift_bridge(swift_name="isBottomMainScreen")]
+    fn is_bottom_main_screen(&self) -> bool;
+
+    #[swift_bridge(swift_name="setResolutionScale")]
+    fn set_resolution_scale(&mut self, val: usize);
+
+    #[swift_bridge(swift_name="resolutionScale")]
+    fn resolution_scale(&self) -> usize;
+
     #[swift_bridge(swift_name="createSaveState")]
     fn create_save_state(&mut self) -> *const u8;
 
@@ -130,13 +179,19 @@ mod ffi {
 
 pub struct MobileEmulator {
   nds: Nds,
-  compressed_len: usize
+  compressed_len: usize,
+  settings: Settings,
+  video_filters: VideoFilters,
+  filtered_size: (usize, usize)
 }
 
 pub fn new_load_state() -> MobileEmulator {
   MobileEmulator {
     nds: Nds::new_load_state(),
-    compressed_len: 0
+    compressed_len: 0,
+    settings: Settings::new(),
+    video_filters: VideoFilters::new(),
+    filtered_size: (0, 0)
   }
 }
 
@@ -165,7 +220,10 @@ impl MobileEmulator {
         audio_buffer,
         mic_samples.clone(),
       ),
-      compressed_len: 0
+      compressed_len: 0,
+      settings: Settings::new(),
+      video_filters: VideoFilters::new(),
+      filtered_size: (0, 0)
     };
 
     emu.nds.init(&game_data.to_vec(), true);
@@ -178,6 +236,8 @@ impl MobileEmulator {
 
     let frame_start = self.nds.arm7_cpu.cycles;
 
+    self.nds.apply_settings(&self.settings);
+
     while !(frame_finished) {
       if !self.nds.paused {
         frame_finished = self.nds.step();
@@ -191,6 +251,10 @@ impl MobileEmulator {
 
     bus.gpu.cap_fps();
 
+    if self.video_filters.is_enabled() {
+      self.filtered_size = self.video_filters.apply_to_screens(&bus.gpu);
+    }
+
     bus.gpu.frame_finished = false;
   }
 
@@ -377,6 +441,70 @@ impl MobileEmulator {
     }
   }
 
+  pub fn set_unlimited_polygons(&mut self, val: bool) {
+    self.settings.unlimited_polygons = val;
+  }
+
+  pub fn set_threaded_rendering(&mut self, val: bool) {
+    self.settings.threaded_rendering = val;
+  }
+
+  pub fn set_scanline_rendering(&mut self, val: bool) {
+    self.settings.scanline_rendering = val;
+  }
+
+  pub fn set_interpolation(&mut self, val: u8) {
+    self.settings.interpolation = Interpolation::from(val);
+  }
+
+  pub fn set_upscaler(&mut self, val: u8) {
+    self.video_filters.upscaler = Upscaler::from(val);
+  }
+
+  pub fn set_upscale_factor(&mut self, val: usize) {
+    self.video_filters.upscale_factor = val;
+  }
+
+  pub fn set_overlay(&mut self, val: u8) {
+    self.video_filters.overlay = Overlay::from(val);
+  }
+
+  pub fn set_color_correction(&mut self, val: u8) {
+    self.video_filters.color_correction = ColorCorrection::from(val);
+  }
+
+  pub fn set_ghosting(&mut self, val: bool) {
+    self.video_filters.ghosting = val;
+  }
+
+  pub fn filters_enabled(&self) -> bool {
+    self.video_filters.is_enabled()
+  }
+
+  pub fn get_filtered_picture_pointer(&self, screen: usize) -> *const u8 {
+    self.video_filters.output(screen).as_ptr()
+  }
+
+  pub fn filtered_width(&self) -> usize {
+    self.filtered_size.0
+  }
+
+  pub fn filtered_height(&self) -> usize {
+    self.filtered_size.1
+  }
+
+  pub fn is_bottom_main_screen(&self) -> bool {
+    self.nds.bus.borrow().gpu.main_screen_hint.screen == Screen::Bottom
+  }
+
+  pub fn set_resolution_scale(&mut self, val: usize) {
+    self.settings.resolution_scale = val;
+  }
+
+  pub fn resolution_scale(&self) -> usize {
+    self.nds.bus.borrow().gpu.resolution_scale()
+  }
+
   pub fn set_pause(&mut self, val: bool) {
     if val {
       self.nds.paused = true;
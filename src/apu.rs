@@ -7,6 +7,7 @@ use std::{
 };
 
 use channel::Channel;
+use interpolation::Interpolation;
 use registers::{
   sound_capture_control_register::SoundCaptureControlRegister,
   sound_control_register::{
@@ -14,6 +15,7 @@ use registers::{
     SoundControlRegister
   }
 };
+use resampler::Resampler;
 use serde::{Deserialize, Serialize};
 
 use crate::scheduler::{
@@ -23,6 +25,8 @@ use crate::scheduler::{
 
 pub mod registers;
 pub mod channel;
+pub mod interpolation;
+pub mod resampler;
 
 pub const NUM_SAMPLES: usize = 8192*2;
 pub const DS_SAMPLE_RATE: usize = 32768;
@@ -115,7 +119,8 @@ pub struct APU {
   #[serde(skip_deserializing)]
   #[serde(skip_serializing)]
   pub audio_buffer: Arc<Mutex<VecDeque<f32>>>,
-  pub phase: f32,
+  #[serde(skip)]
+  resampler: Resampler,
   pub debug_on: bool,
   pub is_paused: bool
 }
@@ -128,7 +133,7 @@ impl APU {
       channels: Self::create_channels(),
       sndcapcnt: [SoundCaptureControlRegister::new(), SoundCaptureControlRegister::new()],
       audio_buffer,
-      phase: 0.0,
+      resampler: Resampler::new(),
       debug_on: false,
       is_paused: false
     };
@@ -141,27 +146,32 @@ impl APU {
     apu
   }
 
+  /// Sets how every channel interpolates between its decoded samples.
+  pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+    for channel in &mut self.channels {
+      channel.interpolation = interpolation;
+    }
+  }
+
   fn resample(&mut self, sample: Sample<f32>) {
-    while self.phase < 1.0 {
-      self.push_sample(sample);
+    let mut audio_buffer = self.audio_buffer.lock().unwrap();
 
-      self.phase += DS_SAMPLE_RATE as f32 / OUT_FREQUENCY as f32;
-    }
-    self.phase -= 1.0;
+    self.resampler.resample(sample, |sample| Self::push_sample(&mut audio_buffer, sample));
   }
 
-  pub fn generate_mixer(&mut self, mixer: &mut Sample<f32>) {
+  /// Mixes every channel except 1 and 3, interpolated to `cycles` if given.
+  pub fn generate_mixer(&mut self, mixer: &mut Sample<f32>, cycles: Option<usize>) {
     if self.channels[0].soundcnt.is_started || self.channels[0].soundcnt.hold_sample {
-      self.channels[0].generate_samples(mixer);
+      self.channels[0].generate_samples(mixer, cycles);
     }
 
     if self.channels[2].soundcnt.is_started || self.channels[2].soundcnt.hold_sample {
-      self.channels[2].generate_samples(mixer);
+      self.channels[2].generate_samples(mixer, cycles);
     }
 
     for i in 4..self.channels.len() {
       if self.channels[i].soundcnt.is_started || self.channels[i].soundcnt.hold_sample {
-        self.channels[i].generate_samples(mixer);
+        self.channels[i].generate_samples(mixer, cycles);
       }
     }
   }
@@ -177,13 +187,15 @@ impl APU {
     let mut ch1 = Sample { left: 0.0, right: 0.0 };
     let mut ch3 = Sample { left: 0.0, right: 0.0 };
 
-    self.generate_mixer(&mut mixer);
+    let cycles = Some(scheduler.cycles - cycles_left);
+
+    self.generate_mixer(&mut mixer, cycles);
 
     if self.channels[1].soundcnt.is_started || self.channels[1].soundcnt.hold_sample {
-      self.channels[1].generate_samples(&mut ch1);
+      self.channels[1].generate_samples(&mut ch1, cycles);
     }
     if self.channels[3].soundcnt.is_started || self.channels[3].soundcnt.hold_sample {
-      self.channels[3].generate_samples(&mut ch3);
+      self.channels[3].generate_samples(&mut ch3, cycles);
     }
 
     if self.soundcnt.output_ch1_to_mixer {
@@ -223,9 +235,7 @@ impl APU {
     sample * master_volume
   }
 
-  fn push_sample(&mut self, sample: Sample<f32>) {
-    let mut audio_buffer = self.audio_buffer.lock().unwrap();
-
+  fn push_sample(audio_buffer: &mut VecDeque<f32>, sample: Sample<f32>) {
     if audio_buffer.len() < NUM_SAMPLES {
       audio_buffer.push_back(sample.left);
     }
@@ -331,7 +341,7 @@ impl APU {
 
           channel.noise_lfsr = None;
           channel.current_psg_value = None;
-          channel.current_sample = 0.0;
+          channel.clear_samples();
 
           channel.current_address = channel.source_address;
           channel.bytes_left = (channel.loop_start as u32 + channel.sound_length) * 4;
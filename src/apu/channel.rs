@@ -6,6 +6,7 @@ use crate::scheduler::{
 };
 
 use super::{
+  interpolation::Interpolation,
   registers::sound_channel_control_register::{
     RepeatMode,
     SoundChannelControlRegister,
@@ -40,7 +41,12 @@ pub struct Channel {
   pub pcm_samples_left: usize,
   pub sample_fifo: u32,
   pub noise_lfsr: Option<u16>,
-  pub current_psg_value: Option<u32>
+  pub current_psg_value: Option<u32>,
+  /// The last four decoded samples, oldest first, and when the newest one was decoded.
+  sample_history: [f32; 4],
+  last_sample_cycles: usize,
+  #[serde(skip)]
+  pub interpolation: Interpolation
 }
 
 impl Channel {
@@ -62,16 +68,52 @@ impl Channel {
       sample_fifo: 0,
       pcm_samples_left: 0,
       noise_lfsr: None,
-      current_psg_value: None
+      current_psg_value: None,
+      sample_history: [0.0; 4],
+      last_sample_cycles: 0,
+      interpolation: Interpolation::None
     }
   }
 
-  pub fn generate_samples(&self, sample: &mut Sample<f32>) {
+  /// Adds the channel's output to `sample`. When `cycles` is given, the output is interpolated
+  /// to that point in time. Sound capture records what the hardware outputs, so it passes None.
+  pub fn generate_samples(&self, sample: &mut Sample<f32>, cycles: Option<usize>) {
     let volume = (self.soundcnt.volume_mul() as f32 / 128.0) / self.soundcnt.volume_div();
     let panning = self.soundcnt.panning_factor() as f32 / 128.0;
 
-    sample.left += self.current_sample * volume * (1.0 - panning);
-    sample.right += self.current_sample * volume * panning;
+    let output = match cycles {
+      Some(cycles) => self.get_interpolated_sample(cycles),
+      None => self.current_sample
+    };
+
+    sample.left += output * volume * (1.0 - panning);
+    sample.right += output * volume * panning;
+  }
+
+  fn get_interpolated_sample(&self, cycles: usize) -> f32 {
+    if self.interpolation == Interpolation::None || self.soundcnt.format == SoundFormat::PSG || self.timer_value == 0 {
+      return self.current_sample;
+    }
+
+    let period = (0x10000 - self.timer_value as usize) << 1;
+
+    let t = (cycles.saturating_sub(self.last_sample_cycles) as f32 / period as f32).min(1.0);
+
+    self.interpolation.interpolate(&self.sample_history, t)
+  }
+
+  fn set_decoded_sample(&mut self, sample: f32, scheduler: &Scheduler, cycles_left: usize) {
+    self.current_sample = sample;
+
+    self.sample_history.rotate_left(1);
+    self.sample_history[3] = sample;
+
+    self.last_sample_cycles = scheduler.cycles - cycles_left;
+  }
+
+  pub fn clear_samples(&mut self) {
+    self.current_sample = 0.0;
+    self.sample_history = [0.0; 4];
   }
 
   pub fn apply_volume(&self) -> f32 {
@@ -158,7 +200,7 @@ impl Channel {
   }
 
   pub fn step_sample_8(&mut self, scheduler: &mut Scheduler, cycles_left: usize) {
-    self.current_sample = (self.sample_fifo as i8 as f32) / i8::MAX as f32;
+    self.set_decoded_sample((self.sample_fifo as i8 as f32) / i8::MAX as f32, scheduler, cycles_left);
     self.sample_fifo >>= 8;
     self.pcm_samples_left -= 1;
 
@@ -174,7 +216,7 @@ impl Channel {
   }
 
   pub fn step_sample_16(&mut self, scheduler: &mut Scheduler, cycles_left: usize) {
-    self.current_sample = (self.sample_fifo as i16 as f32) / i16::MAX as f32;
+    self.set_decoded_sample((self.sample_fifo as i16 as f32) / i16::MAX as f32, scheduler, cycles_left);
 
     self.sample_fifo >>= 16;
     self.pcm_samples_left -= 1;
@@ -234,7 +276,7 @@ impl Channel {
 
     self.adpcm_index = self.adpcm_index.clamp(0, 88);
 
-    self.current_sample = self.adpcm_value as f32 / i16::MAX as f32;
+    self.set_decoded_sample(self.adpcm_value as f32 / i16::MAX as f32, scheduler, cycles_left);
 
     if self.bytes_left == 0 && self.pcm_samples_left == 0 {
       reset = self.handle_end();
@@ -246,7 +288,7 @@ impl Channel {
   }
 
   pub fn reset_audio(&mut self) {
-    self.current_sample = 0.0;
+    self.clear_samples();
     self.soundcnt.is_started = false;
   }
 
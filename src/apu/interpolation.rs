@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+
+/// How quickly the weights of the gaussian kernel fall off with distance. This puts about 64%
+/// of the weight on the nearest sample and 18% on each of its neighbors, close to the SNES' table.
+const GAUSSIAN_FALLOFF: f32 = 1.27;
+
+/*
+  The hardware just holds each decoded sample until the channel's timer steps to the next one,
+  which aliases badly at low sample rates. These modes smooth the output between the decoded
+  samples instead. Each of them goes from the second oldest sample in the history to the one after
+  it, so the cubic and gaussian curves have a sample on either side, at the cost of two samples of
+  latency. They only apply to PCM8, PCM16 and ADPCM channels, the PSG and noise channels are
+  meant to be square.
+*/
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Interpolation {
+  #[default]
+  None,
+  Linear,
+  Cosine,
+  Cubic,
+  Gaussian
+}
+
+impl Interpolation {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => Interpolation::Linear,
+      2 => Interpolation::Cosine,
+      3 => Interpolation::Cubic,
+      4 => Interpolation::Gaussian,
+      _ => Interpolation::None
+    }
+  }
+
+  /// Returns the output between the middle two decoded samples in `history` (oldest first), `t`
+  /// (0 to 1) of the way through the newest sample's period.
+  pub fn interpolate(&self, history: &[f32; 4], t: f32) -> f32 {
+    let [p0, p1, p2, p3] = *history;
+
+    match self {
+      Interpolation::None => p3,
+      Interpolation::Linear => p1 + (p2 - p1) * t,
+      Interpolation::Cosine => {
+        let t = (1.0 - (t * PI).cos()) / 2.0;
+
+        p1 + (p2 - p1) * t
+      }
+      // catmull-rom spline through the middle two samples
+      Interpolation::Cubic => {
+        let a = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+        let b = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+        let c = -0.5 * p0 + 0.5 * p2;
+
+        ((a * t + b) * t + c) * t + p1
+      }
+      Interpolation::Gaussian => {
+        let weight = |distance: f32| (-GAUSSIAN_FALLOFF * distance * distance).exp();
+
+        let weights = [weight(1.0 + t), weight(t), weight(1.0 - t), weight(2.0 - t)];
+
+        let total: f32 = weights.iter().sum();
+
+        (p0 * weights[0] + p1 * weights[1] + p2 * weights[2] + p3 * weights[3]) / total
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Interpolation;
+
+  const SMOOTHED: [Interpolation; 4] = [
+    Interpolation::Linear,
+    Interpolation::Cosine,
+    Interpolation::Cubic,
+    Interpolation::Gaussian
+  ];
+
+  #[test]
+  fn every_mode_uses_the_same_samples() {
+    // a step between the middle two samples is halfway there in the middle of the period
+    let history = [0.0, 0.0, 1.0, 1.0];
+
+    for interpolation in SMOOTHED {
+      let output = interpolation.interpolate(&history, 0.5);
+
+      assert!((output - 0.5).abs() < 1e-6, "{interpolation:?}: {output}");
+    }
+
+    // the curves that go through the samples start on one and end on the next
+    let history = [0.1, 0.4, -0.3, 0.8];
+
+    for interpolation in [Interpolation::Linear, Interpolation::Cosine, Interpolation::Cubic] {
+      let start = interpolation.interpolate(&history, 0.0);
+      let end = interpolation.interpolate(&history, 1.0);
+
+      assert!((start - 0.4).abs() < 1e-6, "{interpolation:?}: {start}");
+      assert!((end + 0.3).abs() < 1e-6, "{interpolation:?}: {end}");
+    }
+  }
+}
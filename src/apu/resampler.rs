@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+
+use super::{Sample, DS_SAMPLE_RATE, OUT_FREQUENCY};
+
+/// Input samples each output sample is made from. Half of them are in the future, so the
+/// output lags the input by that many samples (about 0.5ms).
+const TAPS: usize = 32;
+
+/// Fractional positions between two input samples that get their own set of weights.
+const PHASES: usize = 256;
+
+/// Cutoff frequency as a fraction of the input's Nyquist frequency. Slightly below it, so the
+/// window has room to roll off before anything can fold back.
+const CUTOFF: f32 = 0.9;
+
+/*
+  Converts the 32768 Hz output of the mixer to the 44100 Hz frontends play at. Every output
+  sample is a windowed sinc filter over the input samples around it, which removes the images
+  above 16 kHz that just repeating samples would leave in. The weights for each fractional
+  position are computed up front, for a fixed number of positions.
+*/
+pub struct Resampler {
+  weights: Vec<[f32; TAPS]>,
+  history: [Sample<f32>; TAPS],
+  phase: f32
+}
+
+impl Default for Resampler {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Resampler {
+  pub fn new() -> Self {
+    let mut weights = vec![[0.0; TAPS]; PHASES];
+
+    for (phase, weights) in weights.iter_mut().enumerate() {
+      let offset = phase as f32 / PHASES as f32;
+
+      for (tap, weight) in weights.iter_mut().enumerate() {
+        let distance = tap as f32 - (TAPS / 2 - 1) as f32 - offset;
+
+        *weight = Self::sinc(distance * CUTOFF) * Self::blackman(distance);
+      }
+
+      // every set of weights should keep the volume the same
+      let total: f32 = weights.iter().sum();
+
+      for weight in weights.iter_mut() {
+        *weight /= total;
+      }
+    }
+
+    Self {
+      weights,
+      history: [Sample::<f32>::new(); TAPS],
+      phase: 0.0
+    }
+  }
+
+  /// Adds an input sample, and outputs the samples that fall before the next one.
+  pub fn resample(&mut self, sample: Sample<f32>, mut output: impl FnMut(Sample<f32>)) {
+    self.history.rotate_left(1);
+    self.history[TAPS - 1] = sample;
+
+    while self.phase < 1.0 {
+      let weights = &self.weights[((self.phase * PHASES as f32) as usize).min(PHASES - 1)];
+
+      let mut result = Sample::<f32>::new();
+
+      for (sample, weight) in self.history.iter().zip(weights.iter()) {
+        result.left += sample.left * weight;
+        result.right += sample.right * weight;
+      }
+
+      output(result);
+
+      self.phase += DS_SAMPLE_RATE as f32 / OUT_FREQUENCY as f32;
+    }
+
+    self.phase -= 1.0;
+  }
+
+  fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+      1.0
+    } else {
+      (PI * x).sin() / (PI * x)
+    }
+  }
+
+  /// Window over the taps, zero at the edges of the filter.
+  fn blackman(distance: f32) -> f32 {
+    let x = (distance / (TAPS / 2) as f32 + 1.0) / 2.0;
+
+    if !(0.0..=1.0).contains(&x) {
+      return 0.0;
+    }
+
+    0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos()
+  }
+}
@@ -33,7 +33,7 @@ use crate::{
 use super::{
   dma::{
     dma_channel::{
-      registers::dma_control_register::DmaControlRegister,
+      registers::dma_control_register::{DmaControlRegister, DmaTiming},
       DmaParams
     },
     dma_channels::DmaChannels
@@ -81,6 +81,7 @@ pub mod touchscreen;
 pub mod eeprom;
 pub mod backup_file;
 pub mod firmware_data;
+pub mod io_registers;
 
 pub const ITCM_SIZE: usize = 0x8000;
 pub const DTCM_SIZE: usize = 0x4000;
@@ -461,6 +462,11 @@ impl Bus {
             channel.dma_control.remove(DmaControlRegister::DMA_ENABLE);
           }
 
+          // main memory display DMAs only move a few words at a time, they keep getting requested until the FIFO has the whole line
+          if channel.running && channel.dma_control.dma_start_timing(true) == DmaTiming::MainMemoryDisplay && self.gpu.display_fifo.needs_data() {
+            channel.pending = true;
+          }
+
           if params.should_trigger_irq {
             self.arm9.interrupt_request.request_dma(i);
           }
@@ -804,7 +810,7 @@ impl Bus {
     }
     let mut mixer = Sample::<f32>::new();
     if !self.arm7.apu.sndcapcnt[capture_id].use_channel {
-      self.arm7.apu.generate_mixer(&mut mixer);
+      self.arm7.apu.generate_mixer(&mut mixer, None);
     }
 
     // rust once again not letting me do something simple like let sndcapcnt = &mut self.arm7.apu.sndcapcnt so i have to type that out every single time
@@ -4,10 +4,8 @@ use crate::{
     interrupt_enable_register::InterruptEnableRegister,
     interrupt_request_register::InterruptRequestRegister
   },
-  gpu::registers::{
-    display_3d_control_register::Display3dControlRegister,
-    power_control_register1::PowerControlRegister1
-  }, number::Number
+  gpu::registers::power_control_register1::PowerControlRegister1,
+  number::Number
 };
 
 use super::{cp15::cp15_control_register::CP15ControlRegister, Bus, DTCM_SIZE, ITCM_SIZE, MAIN_MEMORY_SIZE};
@@ -99,8 +97,11 @@ impl Bus {
       0x400_02b4 => self.arm9.sqrt_result,
       0x400_02b8 => self.arm9.sqrt_param as u32,
       0x400_02bc => (self.arm9.sqrt_param >> 32) as u32,
+      0x400_0320 => self.gpu.engine3d.read_rdlines_count() as u32,
       0x400_0440..=0x400_05c8 => 0,
       0x400_0600 => self.gpu.engine3d.read_geometry_status(&mut self.arm9.interrupt_request),
+      0x400_0620..=0x400_062f => self.gpu.engine3d.read_position_test(address & !0x3),
+      0x400_0630..=0x400_0636 => self.gpu.engine3d.read_vector_test(address) as u32 | (self.gpu.engine3d.read_vector_test(address + 2) as u32) << 16,
       0x400_0640..=0x400_067f => self.gpu.engine3d.read_clip_matrix(address),
       0x400_0680..=0x400_06a3 => self.gpu.engine3d.read_vector_matrix(address),
       0x400_1000 => self.gpu.engine_b.dispcnt.read(),
@@ -228,9 +229,11 @@ impl Bus {
       0x400_0290 => self.arm9.div_numerator as u16,
       0x400_02b0 => self.arm9.sqrtcnt.read(),
       0x400_0304 => self.gpu.powcnt1.bits() as u16,
+      0x400_0320 => self.gpu.engine3d.read_rdlines_count(),
       0x400_0604 => self.gpu.engine3d.read_ram_count() as u16,
       0x400_0606 => (self.gpu.engine3d.read_ram_count() >> 16) as u16,
-      0x400_0630..=0x400_0636 => 0, // unimplemented vectest
+      0x400_0620..=0x400_062f => (self.gpu.engine3d.read_position_test(address & !0x3) >> ((address & 0x2) * 8)) as u16,
+      0x400_0630..=0x400_0636 => self.gpu.engine3d.read_vector_test(address & !0x1),
       0x400_1000 => self.gpu.engine_b.dispcnt.read() as u16,
       0x400_1002 => (self.gpu.engine_b.dispcnt.read() >> 16) as u16,
       0x400_1008..=0x400_105f => self.gpu.engine_b.read_register(address),
@@ -349,9 +352,9 @@ impl Bus {
         self.arm9_io_write_16(address, value as u16);
         self.arm9_io_write_16(address + 2, (value >> 16) as u16);
       }
-      0x400_0060 => self.gpu.engine3d.disp3dcnt = Display3dControlRegister::from_bits_retain(value),
+      0x400_0060 => self.gpu.engine3d.write_disp3dcnt(value as u16),
       0x400_0064 => self.gpu.dispcapcnt.write(value),
-      0x400_0068 => (), // ignoring writes to DISP_MMEM_FIFO
+      0x400_0068 => self.gpu.display_fifo.write(value),
       0x400_006c => self.gpu.engine_a.master_brightness.write(value as u16),
       0x400_00b0..=0x400_00ba => self.arm9.dma.write(0, (address - 0x400_00b0) as usize, value, None, &mut self.scheduler),
       0x400_00bc..=0x400_00c6 => self.arm9.dma.write(1, (address - 0x400_00bc) as usize, value, None, &mut self.scheduler),
@@ -460,7 +463,7 @@ impl Bus {
       0x400_0004 => self.gpu.dispstat[1].write(value),
       0x400_0006 => (),
       0x400_0008..=0x400_005f => self.gpu.engine_a.write_register(address, value, None),
-      0x400_0060 => self.gpu.engine3d.disp3dcnt = Display3dControlRegister::from_bits_retain(value as u32),
+      0x400_0060 => self.gpu.engine3d.write_disp3dcnt(value),
       0x400_006c => self.gpu.engine_a.master_brightness.write(value),
       0x400_00b0..=0x400_00ba => {
         let actual_addr = address & !(0x3);
@@ -551,8 +554,8 @@ impl Bus {
 
   pub fn arm9_io_write_8(&mut self, address: u32, value: u8) {
     match address {
-      0x400_004c => self.gpu.mosaic.write(value as u16, 0xff00),
-      0x400_004d => self.gpu.mosaic.write((value as u16) << 8, 0xff),
+      0x400_004c => self.gpu.engine_a.mosaic.write(value as u16, 0xff00),
+      0x400_004d => self.gpu.engine_a.mosaic.write((value as u16) << 8, 0xff),
       0x400_01a1 => self.cartridge.spicnt.write((value as u16) << 8, self.exmem.nds_access_rights == AccessRights::Arm9, Some(0xff)),
       0x400_01a8..=0x400_01af => {
         let byte = address - 0x400_01a8;
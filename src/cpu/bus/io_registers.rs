@@ -0,0 +1,554 @@
+use super::Bus;
+
+/*
+  A table of the I/O registers that can be safely inspected from outside the emulator,
+  along with how their bits are laid out (see GBATEK). Registers are read and written
+  through the same paths the CPUs use, so writes have the same side effects they would
+  have on hardware. To keep an edit from touching more than the bits it changed,
+  acknowledge bits (ie: IF) are only written as 1 when the edit clears them, and bits that
+  start something when set (ie: DMAxCNT enable) keep their live value unless they are
+  the ones being edited. Registers where even a read has side effects (IPC FIFO receive,
+  SPI data, etc) are left out, unless they can be peeked at without them (GXSTAT), and
+  ones where a write can't be made safe (ROMCTRL starts a card command) are read only.
+*/
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum IoCpu {
+  Arm9,
+  Arm7
+}
+
+#[derive(Copy, Clone)]
+pub struct IoField {
+  pub name: &'static str,
+  pub start: u32,
+  pub length: u32
+}
+
+impl IoField {
+  const fn new(name: &'static str, start: u32, length: u32) -> Self {
+    Self {
+      name,
+      start,
+      length
+    }
+  }
+
+  pub fn mask(&self) -> u32 {
+    (((1u64 << self.length) - 1) << self.start) as u32
+  }
+
+  pub fn get(&self, value: u32) -> u32 {
+    (value & self.mask()) >> self.start
+  }
+
+  pub fn set(&self, value: u32, field_value: u32) -> u32 {
+    (value & !self.mask()) | ((field_value << self.start) & self.mask())
+  }
+}
+
+pub struct IoRegister {
+  pub name: String,
+  pub address: u32,
+  pub cpu: IoCpu,
+  // in bytes
+  pub size: u32,
+  pub read_only: bool,
+  // bits that are write 1 to clear
+  pub acknowledge_mask: u32,
+  // bits that keep their live value when they aren't being edited
+  pub preserve_mask: u32,
+  pub fields: &'static [IoField]
+}
+
+impl IoRegister {
+  fn new(name: &str, address: u32, cpu: IoCpu, size: u32, fields: &'static [IoField]) -> Self {
+    Self {
+      name: name.to_string(),
+      address,
+      cpu,
+      size,
+      read_only: false,
+      acknowledge_mask: 0,
+      preserve_mask: 0,
+      fields
+    }
+  }
+
+  fn read_only(mut self) -> Self {
+    self.read_only = true;
+
+    self
+  }
+
+  fn acknowledge_on_write(mut self, mask: u32) -> Self {
+    self.acknowledge_mask = mask;
+
+    self
+  }
+
+  fn preserve(mut self, mask: u32) -> Self {
+    self.preserve_mask = mask;
+
+    self
+  }
+}
+
+const DISPCNT_FIELDS: &[IoField] = &[
+  IoField::new("BG mode", 0, 3),
+  IoField::new("BG0 2D/3D", 3, 1),
+  IoField::new("Tile OBJ mapping", 4, 1),
+  IoField::new("Bitmap OBJ 2D dimension", 5, 1),
+  IoField::new("Bitmap OBJ mapping", 6, 1),
+  IoField::new("Forced blank", 7, 1),
+  IoField::new("BG0 enable", 8, 1),
+  IoField::new("BG1 enable", 9, 1),
+  IoField::new("BG2 enable", 10, 1),
+  IoField::new("BG3 enable", 11, 1),
+  IoField::new("OBJ enable", 12, 1),
+  IoField::new("Window 0 enable", 13, 1),
+  IoField::new("Window 1 enable", 14, 1),
+  IoField::new("OBJ window enable", 15, 1),
+  IoField::new("Display mode", 16, 2),
+  IoField::new("VRAM block", 18, 2),
+  IoField::new("Tile OBJ 1D boundary", 20, 2),
+  IoField::new("Bitmap OBJ 1D boundary", 22, 1),
+  IoField::new("OBJ processing during HBlank", 23, 1),
+  IoField::new("Character base", 24, 3),
+  IoField::new("Screen base", 27, 3),
+  IoField::new("BG extended palettes", 30, 1),
+  IoField::new("OBJ extended palettes", 31, 1)
+];
+
+const DISPSTAT_FIELDS: &[IoField] = &[
+  IoField::new("VBlank flag", 0, 1),
+  IoField::new("HBlank flag", 1, 1),
+  IoField::new("VCounter flag", 2, 1),
+  IoField::new("VBlank IRQ enable", 3, 1),
+  IoField::new("HBlank IRQ enable", 4, 1),
+  IoField::new("VCounter IRQ enable", 5, 1),
+  IoField::new("VCount setting bit 8", 7, 1),
+  IoField::new("VCount setting", 8, 8)
+];
+
+const VCOUNT_FIELDS: &[IoField] = &[
+  IoField::new("Current scanline", 0, 9)
+];
+
+const BGCNT_FIELDS: &[IoField] = &[
+  IoField::new("Priority", 0, 2),
+  IoField::new("Character base", 2, 4),
+  IoField::new("Mosaic", 6, 1),
+  IoField::new("256 colors", 7, 1),
+  IoField::new("Screen base", 8, 5),
+  IoField::new("Ext palette slot/overflow", 13, 1),
+  IoField::new("Screen size", 14, 2)
+];
+
+const DISP3DCNT_FIELDS: &[IoField] = &[
+  IoField::new("Texture mapping", 0, 1),
+  IoField::new("Polygon attribute shading", 1, 1),
+  IoField::new("Alpha test", 2, 1),
+  IoField::new("Alpha blending", 3, 1),
+  IoField::new("Anti aliasing", 4, 1),
+  IoField::new("Edge marking", 5, 1),
+  IoField::new("Fog alpha only", 6, 1),
+  IoField::new("Fog enable", 7, 1),
+  IoField::new("Fog depth shift", 8, 4),
+  IoField::new("Color buffer underflow", 12, 1),
+  IoField::new("Polygon/vertex RAM overflow", 13, 1),
+  IoField::new("Rear-plane bitmap mode", 14, 1)
+];
+
+const MASTER_BRIGHT_FIELDS: &[IoField] = &[
+  IoField::new("Factor", 0, 5),
+  IoField::new("Mode", 14, 2)
+];
+
+const GXSTAT_FIELDS: &[IoField] = &[
+  IoField::new("Test busy", 0, 1),
+  IoField::new("Box test result", 1, 1),
+  IoField::new("Position/vector stack level", 8, 5),
+  IoField::new("Projection stack level", 13, 1),
+  IoField::new("Matrix stack busy", 14, 1),
+  IoField::new("Matrix stack error", 15, 1),
+  IoField::new("FIFO entries", 16, 9),
+  IoField::new("FIFO less than half full", 25, 1),
+  IoField::new("FIFO empty", 26, 1),
+  IoField::new("Geometry engine busy", 27, 1),
+  IoField::new("FIFO IRQ", 30, 2)
+];
+
+const ARM9_DMACNT_FIELDS: &[IoField] = &[
+  IoField::new("Word count", 0, 21),
+  IoField::new("Destination control", 21, 2),
+  IoField::new("Source control", 23, 2),
+  IoField::new("Repeat", 25, 1),
+  IoField::new("32 bit transfer", 26, 1),
+  IoField::new("Start timing", 27, 3),
+  IoField::new("IRQ enable", 30, 1),
+  IoField::new("Enable", 31, 1)
+];
+
+const ARM7_DMACNT_FIELDS: &[IoField] = &[
+  IoField::new("Word count", 0, 14),
+  IoField::new("Destination control", 21, 2),
+  IoField::new("Source control", 23, 2),
+  IoField::new("Repeat", 25, 1),
+  IoField::new("32 bit transfer", 26, 1),
+  IoField::new("Start timing", 28, 2),
+  IoField::new("IRQ enable", 30, 1),
+  IoField::new("Enable", 31, 1)
+];
+
+const ARM7_DMA3CNT_FIELDS: &[IoField] = &[
+  IoField::new("Word count", 0, 16),
+  IoField::new("Destination control", 21, 2),
+  IoField::new("Source control", 23, 2),
+  IoField::new("Repeat", 25, 1),
+  IoField::new("32 bit transfer", 26, 1),
+  IoField::new("Start timing", 28, 2),
+  IoField::new("IRQ enable", 30, 1),
+  IoField::new("Enable", 31, 1)
+];
+
+const ADDRESS_FIELDS: &[IoField] = &[
+  IoField::new("Address", 0, 28)
+];
+
+const TIMER_COUNTER_FIELDS: &[IoField] = &[
+  IoField::new("Counter/reload", 0, 16)
+];
+
+const TIMER_CONTROL_FIELDS: &[IoField] = &[
+  IoField::new("Prescaler", 0, 2),
+  IoField::new("Count-up timing", 2, 1),
+  IoField::new("IRQ enable", 6, 1),
+  IoField::new("Start", 7, 1)
+];
+
+const KEYINPUT_FIELDS: &[IoField] = &[
+  IoField::new("A", 0, 1),
+  IoField::new("B", 1, 1),
+  IoField::new("Select", 2, 1),
+  IoField::new("Start", 3, 1),
+  IoField::new("Right", 4, 1),
+  IoField::new("Left", 5, 1),
+  IoField::new("Up", 6, 1),
+  IoField::new("Down", 7, 1),
+  IoField::new("R", 8, 1),
+  IoField::new("L", 9, 1)
+];
+
+const EXTKEYIN_FIELDS: &[IoField] = &[
+  IoField::new("X", 0, 1),
+  IoField::new("Y", 1, 1),
+  IoField::new("Debug button", 3, 1),
+  IoField::new("Pen down", 6, 1),
+  IoField::new("Hinge closed", 7, 1)
+];
+
+const IPCSYNC_FIELDS: &[IoField] = &[
+  IoField::new("Data input", 0, 4),
+  IoField::new("Data output", 8, 4),
+  IoField::new("Send IRQ", 13, 1),
+  IoField::new("IRQ enable", 14, 1)
+];
+
+const IPCFIFOCNT_FIELDS: &[IoField] = &[
+  IoField::new("Send FIFO empty", 0, 1),
+  IoField::new("Send FIFO full", 1, 1),
+  IoField::new("Send FIFO empty IRQ", 2, 1),
+  IoField::new("Send FIFO clear", 3, 1),
+  IoField::new("Receive FIFO empty", 8, 1),
+  IoField::new("Receive FIFO full", 9, 1),
+  IoField::new("Receive FIFO not empty IRQ", 10, 1),
+  IoField::new("Error", 14, 1),
+  IoField::new("Enable", 15, 1)
+];
+
+const AUXSPICNT_FIELDS: &[IoField] = &[
+  IoField::new("Baudrate", 0, 2),
+  IoField::new("Hold chipselect", 6, 1),
+  IoField::new("Busy", 7, 1),
+  IoField::new("Slot mode", 13, 1),
+  IoField::new("Transfer ready IRQ", 14, 1),
+  IoField::new("Slot enable", 15, 1)
+];
+
+const ROMCTRL_FIELDS: &[IoField] = &[
+  IoField::new("KEY1 gap1 length", 0, 13),
+  IoField::new("KEY2 encrypt data", 13, 1),
+  IoField::new("KEY2 apply seed", 15, 1),
+  IoField::new("KEY1 gap2 length", 16, 6),
+  IoField::new("KEY2 encrypt command", 22, 1),
+  IoField::new("Data word ready", 23, 1),
+  IoField::new("Data block size", 24, 3),
+  IoField::new("Transfer clock rate", 27, 1),
+  IoField::new("KEY1 gap clocks", 28, 1),
+  IoField::new("Release reset", 29, 1),
+  IoField::new("Write", 30, 1),
+  IoField::new("Block busy", 31, 1)
+];
+
+const SPICNT_FIELDS: &[IoField] = &[
+  IoField::new("Baudrate", 0, 2),
+  IoField::new("Busy", 7, 1),
+  IoField::new("Device select", 8, 2),
+  IoField::new("16 bit transfer", 10, 1),
+  IoField::new("Chipselect hold", 11, 1),
+  IoField::new("IRQ enable", 14, 1),
+  IoField::new("Enable", 15, 1)
+];
+
+const EXMEMCNT_FIELDS: &[IoField] = &[
+  IoField::new("GBA slot SRAM access time", 0, 2),
+  IoField::new("GBA slot ROM 1st access time", 2, 2),
+  IoField::new("GBA slot ROM 2nd access time", 4, 1),
+  IoField::new("GBA slot PHI output", 5, 2),
+  IoField::new("GBA slot access (ARM7)", 7, 1),
+  IoField::new("NDS slot access (ARM7)", 11, 1),
+  IoField::new("Main memory interface mode", 14, 1),
+  IoField::new("Main memory priority (ARM7)", 15, 1)
+];
+
+const IME_FIELDS: &[IoField] = &[
+  IoField::new("Enable", 0, 1)
+];
+
+const INTERRUPT_FIELDS: &[IoField] = &[
+  IoField::new("VBlank", 0, 1),
+  IoField::new("HBlank", 1, 1),
+  IoField::new("VCounter", 2, 1),
+  IoField::new("Timer 0", 3, 1),
+  IoField::new("Timer 1", 4, 1),
+  IoField::new("Timer 2", 5, 1),
+  IoField::new("Timer 3", 6, 1),
+  IoField::new("SIO/RTC", 7, 1),
+  IoField::new("DMA 0", 8, 1),
+  IoField::new("DMA 1", 9, 1),
+  IoField::new("DMA 2", 10, 1),
+  IoField::new("DMA 3", 11, 1),
+  IoField::new("Keypad", 12, 1),
+  IoField::new("GBA slot", 13, 1),
+  IoField::new("IPC sync", 16, 1),
+  IoField::new("IPC send FIFO empty", 17, 1),
+  IoField::new("IPC receive FIFO not empty", 18, 1),
+  IoField::new("Card transfer complete", 19, 1),
+  IoField::new("Card IREQ_MC", 20, 1),
+  IoField::new("Geometry FIFO", 21, 1),
+  IoField::new("Screens unfolding", 22, 1),
+  IoField::new("SPI bus", 23, 1),
+  IoField::new("Wifi", 24, 1)
+];
+
+const VRAMCNT_FIELDS: &[IoField] = &[
+  IoField::new("MST", 0, 3),
+  IoField::new("Offset", 3, 2),
+  IoField::new("Enable", 7, 1)
+];
+
+const WRAMCNT_FIELDS: &[IoField] = &[
+  IoField::new("Shared WRAM mode", 0, 2)
+];
+
+const VRAMSTAT_FIELDS: &[IoField] = &[
+  IoField::new("Bank C mapped to ARM7", 0, 1),
+  IoField::new("Bank D mapped to ARM7", 1, 1),
+  IoField::new("Shared WRAM mode", 8, 2)
+];
+
+const DIVCNT_FIELDS: &[IoField] = &[
+  IoField::new("Mode", 0, 2),
+  IoField::new("Division by zero", 14, 1),
+  IoField::new("Busy", 15, 1)
+];
+
+const SQRTCNT_FIELDS: &[IoField] = &[
+  IoField::new("64 bit mode", 0, 1),
+  IoField::new("Busy", 15, 1)
+];
+
+const POWCNT1_FIELDS: &[IoField] = &[
+  IoField::new("LCDs enable", 0, 1),
+  IoField::new("2D engine A", 1, 1),
+  IoField::new("3D rendering engine", 2, 1),
+  IoField::new("3D geometry engine", 3, 1),
+  IoField::new("2D engine B", 9, 1),
+  IoField::new("Display swap", 15, 1)
+];
+
+const RDLINES_COUNT_FIELDS: &[IoField] = &[
+  IoField::new("Minimum buffered lines", 0, 6)
+];
+
+const POWCNT2_FIELDS: &[IoField] = &[
+  IoField::new("Sound speakers", 0, 1),
+  IoField::new("Wifi", 1, 1)
+];
+
+const POSTFLG_FIELDS: &[IoField] = &[
+  IoField::new("First boot", 0, 1)
+];
+
+const SOUNDCNT_CHANNEL_FIELDS: &[IoField] = &[
+  IoField::new("Volume multiplier", 0, 7),
+  IoField::new("Volume divider", 8, 2),
+  IoField::new("Hold", 15, 1),
+  IoField::new("Panning", 16, 7),
+  IoField::new("Wave duty", 24, 3),
+  IoField::new("Repeat mode", 27, 2),
+  IoField::new("Format", 29, 2),
+  IoField::new("Start", 31, 1)
+];
+
+const SOUNDCNT_FIELDS: &[IoField] = &[
+  IoField::new("Master volume", 0, 7),
+  IoField::new("Left output", 8, 2),
+  IoField::new("Right output", 10, 2),
+  IoField::new("Output channel 1 to mixer", 12, 1),
+  IoField::new("Output channel 3 to mixer", 13, 1),
+  IoField::new("Master enable", 15, 1)
+];
+
+const SOUNDBIAS_FIELDS: &[IoField] = &[
+  IoField::new("Bias level", 0, 10)
+];
+
+pub fn get_io_registers() -> Vec<IoRegister> {
+  let mut registers = Vec::new();
+
+  for (base_name, base_address) in [("A", 0x400_0000), ("B", 0x400_1000)] {
+    let cpu = IoCpu::Arm9;
+
+    registers.push(IoRegister::new(&format!("DISPCNT ({base_name})"), base_address, cpu, 4, DISPCNT_FIELDS));
+
+    for bg in 0..4 {
+      registers.push(IoRegister::new(&format!("BG{bg}CNT ({base_name})"), base_address + 8 + bg * 2, cpu, 2, BGCNT_FIELDS));
+    }
+
+    registers.push(IoRegister::new(&format!("MASTER_BRIGHT ({base_name})"), base_address + 0x6c, cpu, 2, MASTER_BRIGHT_FIELDS));
+  }
+
+  registers.push(IoRegister::new("DISP3DCNT", 0x400_0060, IoCpu::Arm9, 2, DISP3DCNT_FIELDS));
+  registers.push(IoRegister::new("GXSTAT", 0x400_0600, IoCpu::Arm9, 4, GXSTAT_FIELDS).acknowledge_on_write(1 << 15));
+
+  for (i, axis) in ["X", "Y", "Z", "W"].iter().enumerate() {
+    registers.push(IoRegister::new(&format!("POS_RESULT_{axis}"), 0x400_0620 + i as u32 * 4, IoCpu::Arm9, 4, &[]).read_only());
+  }
+
+  for (i, axis) in ["X", "Y", "Z"].iter().enumerate() {
+    registers.push(IoRegister::new(&format!("VEC_RESULT_{axis}"), 0x400_0630 + i as u32 * 2, IoCpu::Arm9, 2, &[]).read_only());
+  }
+
+  registers.push(IoRegister::new("POWCNT1", 0x400_0304, IoCpu::Arm9, 2, POWCNT1_FIELDS));
+  registers.push(IoRegister::new("RDLINES_COUNT", 0x400_0320, IoCpu::Arm9, 2, RDLINES_COUNT_FIELDS).read_only());
+
+  for (bank, name) in ["A", "B", "C", "D", "E", "F", "G"].iter().enumerate() {
+    registers.push(IoRegister::new(&format!("VRAMCNT_{name}"), 0x400_0240 + bank as u32, IoCpu::Arm9, 1, VRAMCNT_FIELDS));
+  }
+
+  registers.push(IoRegister::new("WRAMCNT", 0x400_0247, IoCpu::Arm9, 1, WRAMCNT_FIELDS));
+  registers.push(IoRegister::new("VRAMCNT_H", 0x400_0248, IoCpu::Arm9, 1, VRAMCNT_FIELDS));
+  registers.push(IoRegister::new("VRAMCNT_I", 0x400_0249, IoCpu::Arm9, 1, VRAMCNT_FIELDS));
+
+  registers.push(IoRegister::new("DIVCNT", 0x400_0280, IoCpu::Arm9, 2, DIVCNT_FIELDS));
+  registers.push(IoRegister::new("SQRTCNT", 0x400_02b0, IoCpu::Arm9, 2, SQRTCNT_FIELDS));
+
+  registers.push(IoRegister::new("POWCNT2", 0x400_0304, IoCpu::Arm7, 2, POWCNT2_FIELDS));
+  registers.push(IoRegister::new("VRAMSTAT/WRAMSTAT", 0x400_0240, IoCpu::Arm7, 2, VRAMSTAT_FIELDS).read_only());
+  registers.push(IoRegister::new("EXTKEYIN", 0x400_0136, IoCpu::Arm7, 2, EXTKEYIN_FIELDS).read_only());
+  registers.push(IoRegister::new("SPICNT", 0x400_01c0, IoCpu::Arm7, 2, SPICNT_FIELDS));
+
+  for channel in 0..16 {
+    registers.push(IoRegister::new(&format!("SOUND{channel}CNT"), 0x400_0400 + channel * 0x10, IoCpu::Arm7, 4, SOUNDCNT_CHANNEL_FIELDS).preserve(1 << 31));
+  }
+
+  registers.push(IoRegister::new("SOUNDCNT", 0x400_0500, IoCpu::Arm7, 2, SOUNDCNT_FIELDS));
+  registers.push(IoRegister::new("SOUNDBIAS", 0x400_0504, IoCpu::Arm7, 2, SOUNDBIAS_FIELDS));
+
+  // registers that both cpus have their own copy of
+  for cpu in [IoCpu::Arm9, IoCpu::Arm7] {
+    registers.push(IoRegister::new("DISPSTAT", 0x400_0004, cpu, 2, DISPSTAT_FIELDS));
+    registers.push(IoRegister::new("VCOUNT", 0x400_0006, cpu, 2, VCOUNT_FIELDS).read_only());
+
+    for channel in 0..4 {
+      let base_address = 0x400_00b0 + channel * 0xc;
+
+      let dmacnt_fields = match (cpu, channel) {
+        (IoCpu::Arm9, _) => ARM9_DMACNT_FIELDS,
+        (IoCpu::Arm7, 3) => ARM7_DMA3CNT_FIELDS,
+        (IoCpu::Arm7, _) => ARM7_DMACNT_FIELDS
+      };
+
+      registers.push(IoRegister::new(&format!("DMA{channel}SAD"), base_address, cpu, 4, ADDRESS_FIELDS));
+      registers.push(IoRegister::new(&format!("DMA{channel}DAD"), base_address + 4, cpu, 4, ADDRESS_FIELDS));
+      registers.push(IoRegister::new(&format!("DMA{channel}CNT"), base_address + 8, cpu, 4, dmacnt_fields).preserve(1 << 31));
+    }
+
+    for timer in 0..4 {
+      let base_address = 0x400_0100 + timer * 4;
+
+      registers.push(IoRegister::new(&format!("TM{timer}CNT_L"), base_address, cpu, 2, TIMER_COUNTER_FIELDS));
+      registers.push(IoRegister::new(&format!("TM{timer}CNT_H"), base_address + 2, cpu, 2, TIMER_CONTROL_FIELDS).preserve(1 << 7));
+    }
+
+    registers.push(IoRegister::new("KEYINPUT", 0x400_0130, cpu, 2, KEYINPUT_FIELDS).read_only());
+    registers.push(IoRegister::new("IPCSYNC", 0x400_0180, cpu, 2, IPCSYNC_FIELDS));
+    registers.push(IoRegister::new("IPCFIFOCNT", 0x400_0184, cpu, 2, IPCFIFOCNT_FIELDS).acknowledge_on_write(1 << 14));
+    registers.push(IoRegister::new("AUXSPICNT", 0x400_01a0, cpu, 2, AUXSPICNT_FIELDS));
+    registers.push(IoRegister::new("ROMCTRL", 0x400_01a4, cpu, 4, ROMCTRL_FIELDS).read_only());
+    registers.push(IoRegister::new("EXMEMCNT", 0x400_0204, cpu, 2, EXMEMCNT_FIELDS));
+    registers.push(IoRegister::new("IME", 0x400_0208, cpu, 2, IME_FIELDS));
+    registers.push(IoRegister::new("IE", 0x400_0210, cpu, 4, INTERRUPT_FIELDS));
+    registers.push(IoRegister::new("IF", 0x400_0214, cpu, 4, INTERRUPT_FIELDS).acknowledge_on_write(0xffff_ffff));
+    registers.push(IoRegister::new("POSTFLG", 0x400_0300, cpu, 2, POSTFLG_FIELDS));
+  }
+
+  registers
+}
+
+impl Bus {
+  pub fn read_io_register(&mut self, register: &IoRegister) -> u32 {
+    // reading GXSTAT from the cpu checks the geometry fifo irq
+    if register.cpu == IoCpu::Arm9 && register.address == 0x400_0600 {
+      return self.gpu.engine3d.peek_geometry_status();
+    }
+
+    match (register.cpu, register.size) {
+      (IoCpu::Arm9, 1) => self.arm9_mem_read_8(register.address) as u32,
+      (IoCpu::Arm9, 2) => self.arm9_mem_read_16(register.address) as u32,
+      (IoCpu::Arm9, _) => self.arm9_mem_read_32(register.address),
+      (IoCpu::Arm7, 1) => self.arm7_mem_read_8(register.address) as u32,
+      (IoCpu::Arm7, 2) => self.arm7_mem_read_16(register.address) as u32,
+      (IoCpu::Arm7, _) => self.arm7_mem_read_32(register.address)
+    }
+  }
+
+  /*
+    edited_mask holds the bits the user changed. acknowledge bits are written as 1 only
+    where the edit cleared them, and preserved bits are rewritten with their live value
+    unless they were edited, so an edit never acknowledges or retriggers anything else.
+  */
+  pub fn write_io_register(&mut self, register: &IoRegister, value: u32, edited_mask: u32) {
+    if register.read_only {
+      return;
+    }
+
+    let live_value = self.read_io_register(register);
+
+    let kept = register.preserve_mask & !edited_mask;
+    let acknowledged = register.acknowledge_mask & edited_mask & !value;
+
+    let value = (value & !kept & !register.acknowledge_mask) | (live_value & kept) | acknowledged;
+
+    match (register.cpu, register.size) {
+      (IoCpu::Arm9, 1) => self.arm9_mem_write_8(register.address, value as u8),
+      (IoCpu::Arm9, 2) => self.arm9_mem_write_16(register.address, value as u16),
+      (IoCpu::Arm9, _) => self.arm9_mem_write_32(register.address, value),
+      (IoCpu::Arm7, 1) => self.arm7_mem_write_8(register.address, value as u8),
+      (IoCpu::Arm7, 2) => self.arm7_mem_write_16(register.address, value as u16),
+      (IoCpu::Arm7, _) => self.arm7_mem_write_32(register.address, value)
+    }
+  }
+}
@@ -18,4 +18,14 @@ impl MosaicRegister {
   pub fn read(&self) -> u16 {
     self.val
   }
-}
\ No newline at end of file
+
+  /// Width and height of the mosaic blocks used by backgrounds, in pixels.
+  pub fn bg_size(&self) -> (u16, u16) {
+    ((self.val & 0xf) + 1, ((self.val >> 4) & 0xf) + 1)
+  }
+
+  /// Width and height of the mosaic blocks used by objects, in pixels.
+  pub fn obj_size(&self) -> (u16, u16) {
+    (((self.val >> 8) & 0xf) + 1, ((self.val >> 12) & 0xf) + 1)
+  }
+}
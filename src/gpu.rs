@@ -2,8 +2,10 @@ use std::{thread::sleep, time::{Duration, SystemTime, UNIX_EPOCH}};
 
 
 use crate::number::Number;
+use display_fifo::DisplayFifo;
+use main_screen::MainScreenHint;
 use engine_2d::Engine2d;
-use engine_3d::Engine3d;
+use engine_3d::{upscaling::MAX_RESOLUTION_SCALE, Engine3d};
 use registers::{
   display_capture_control_register::{
     CaptureSource, DisplayCaptureControlRegister, ScreenSourceA, ScreenSourceB
@@ -25,10 +27,7 @@ use crate::{
     dma::{
       dma_channel::registers::dma_control_register::DmaTiming,
       dma_channels::DmaChannels
-    }, registers::{
-      interrupt_request_register::InterruptRequestRegister,
-      mosaic_register::MosaicRegister
-    }
+    }, registers::interrupt_request_register::InterruptRequestRegister
   },
   scheduler::{
     EventType,
@@ -41,6 +40,8 @@ pub mod engine_2d;
 pub mod engine_3d;
 pub mod vram;
 pub mod color;
+pub mod display_fifo;
+pub mod main_screen;
 
 const NUM_LINES: u16 = 263;
 
@@ -102,8 +103,9 @@ pub struct GPU {
   pub vram: VRam,
   pub vcount: u16,
   pub dispcapcnt: DisplayCaptureControlRegister,
-  pub mosaic: MosaicRegister,
   pub is_capturing: bool,
+  pub display_fifo: DisplayFifo,
+  pub main_screen_hint: MainScreenHint,
   previous_time: u128
 }
 
@@ -127,8 +129,9 @@ impl GPU {
       vcount: 0,
       frame_finished: false,
       vram: VRam::new(),
-      mosaic: MosaicRegister::new(),
       is_capturing: false,
+      display_fifo: DisplayFifo::new(),
+      main_screen_hint: MainScreenHint::new(),
       previous_time: 0
     };
 
@@ -137,6 +140,20 @@ impl GPU {
     gpu
   }
 
+  /// How many times larger than the native resolution the engines' `pixels` are in each direction.
+  pub fn resolution_scale(&self) -> usize {
+    self.engine_a.resolution_scale()
+  }
+
+  /// Renders 3D at a multiple of the native resolution, with the 2D engines composited on top at the same scale.
+  pub fn set_resolution_scale(&mut self, scale: usize) {
+    let scale = scale.clamp(1, MAX_RESOLUTION_SCALE);
+
+    self.engine_a.set_resolution_scale(scale);
+    self.engine_b.set_resolution_scale(scale);
+    self.engine3d.set_resolution_scale(scale);
+  }
+
   pub fn cap_fps(&mut self) {
     let current_time = SystemTime::now()
       .duration_since(UNIX_EPOCH)
@@ -215,7 +232,19 @@ impl GPU {
       self.engine_b.on_end_vblank();
     }
 
+    // when rendering a line at a time, the 3D engine keeps working through the whole frame
+    self.engine3d.run_scanline(&self.vram);
+
+    if self.vcount < SCREEN_HEIGHT && self.uses_display_fifo() {
+      for dma in dma_channels.iter_mut() {
+        dma.notify_gpu_event(DmaTiming::MainMemoryDisplay);
+      }
+    }
+
     if self.vcount == 0 {
+      // a frame rendered on another thread has to be done before the first line gets composited
+      self.engine3d.finish_rendering();
+
       self.is_capturing = self.dispcapcnt.capture_enable;
 
       for dispstat in &mut self.dispstat {
@@ -261,32 +290,32 @@ impl GPU {
     }
   }
 
+  /// Whether the main memory display FIFO is read on this frame, either for display mode 3 or for capturing.
+  fn uses_display_fifo(&self) -> bool {
+    self.engine_a.dispcnt.display_mode == DisplayMode::Mode3 ||
+      (self.is_capturing && self.dispcapcnt.source_b == ScreenSourceB::MainMemoryDisplayFifo)
+  }
+
   fn start_capture_image(&mut self) {
     let width = self.dispcapcnt.get_capture_width() as usize;
     let start_address = self.vcount as usize * SCREEN_WIDTH as usize;
     let block = self.engine_a.dispcnt.vram_block;
 
-    if self.dispcapcnt.source_b == ScreenSourceB::MainMemoryDisplayFifo {
-      todo!("main memory display fifo not implemented");
-    }
-
+    // when rendering above native resolution, both sources are downsampled back to what the hardware would see.
+    // they're 6 bits per channel and only get reduced to 5 bits when written
     fn get_3d_pixel(address: usize, _: &Engine2d<false>, engine3d: &Engine3d) -> u16 {
-      if let Some(color) = engine3d.frame_buffer[address].color {
-        return (color.r & 0x1f) as u16 | (color.g as u16 & 0x1f) << 5 | (color.b as u16 & 0x1f) << 10
+      if let Some(color) = engine3d.get_native_color(address) {
+        return color.to_rgb15();
       }
       0
     }
 
     fn get_pixel(address: usize, engine_a: &Engine2d<false>, _: &Engine3d) -> u16 {
-      let r = engine_a.pixels[3 * address] >> 3;
-      let g = engine_a.pixels[3 * address + 1] >> 3;
-      let b = engine_a.pixels[3 * address + 2] >> 3;
-
-      (r & 0x1f) as u16 | (g as u16 & 0x1f) << 5 | (b as u16 & 0x1f) << 10
+      engine_a.capture_line[address % SCREEN_WIDTH as usize].to_rgb15()
     }
 
     fn get_alpha_3d(address: usize, _: &Engine2d<false>, engine3d: &Engine3d) -> u8 {
-      if let Some(color) = engine3d.frame_buffer[address].color {
+      if let Some(color) = engine3d.get_native_color(address) {
         if let Some(alpha) = color.alpha {
           if alpha == 0 {
             return 0;
@@ -317,7 +346,14 @@ impl GPU {
 
     let mut source_b: [u8; 2 * SCREEN_WIDTH as usize] = [0; 2 * SCREEN_WIDTH as usize];
 
-    source_b[..2 * width].copy_from_slice(&self.vram.banks[block as usize][read_offset..read_offset + 2 * width]);
+    if self.dispcapcnt.source_b == ScreenSourceB::MainMemoryDisplayFifo {
+      for (i, pixel) in self.display_fifo.line[..width].iter().enumerate() {
+        source_b[2 * i] = *pixel as u8;
+        source_b[2 * i + 1] = (*pixel >> 8) as u8;
+      }
+    } else {
+      source_b[..2 * width].copy_from_slice(&self.vram.banks[block as usize][read_offset..read_offset + 2 * width]);
+    }
 
     let write_offset = 2 * start_address as usize + self.dispcapcnt.vram_write_offset as usize;
     let write_block = self.dispcapcnt.vram_write_block as usize;
@@ -327,9 +363,11 @@ impl GPU {
         Dest_Intensity = (  (SrcA_Intensitity * SrcA_Alpha * EVA)
           + (SrcB_Intensitity * SrcB_Alpha * EVB) ) / 16
         */
-      ((channel_a * a_alpha * eva + channel_b * b_alpha * evb) / 16) as u8
+      ((channel_a * a_alpha * eva + channel_b * b_alpha * evb) / 16).min(0x1f) as u8
     }
 
+    let (eva, evb) = (self.dispcapcnt.eva.min(16) as u16, self.dispcapcnt.evb.min(16) as u16);
+
     // finally transfer the capture image!
     match self.dispcapcnt.capture_source {
       CaptureSource::SourceA => {
@@ -363,7 +401,7 @@ impl GPU {
 
           let alpha_a = alpha_source(alpha_address, &self.engine_a, &self.engine3d);
 
-          let pixel_b = source_b[index] as u16 | (source_b[index] as u16) << 8;
+          let pixel_b = source_b[2 * index] as u16 | (source_b[2 * index + 1] as u16) << 8;
 
           let alpha_b = (pixel_b >> 15 & 0b1) as u8;
 
@@ -381,29 +419,29 @@ impl GPU {
             r_b as u16,
             alpha_a as u16,
             alpha_b as u16,
-            self.dispcapcnt.eva as u16,
-            self.dispcapcnt.evb as u16
+            eva,
+            evb
           );
           let new_g = process_channels(
             g_a as u16,
             g_b as u16,
             alpha_a as u16,
             alpha_b as u16,
-            self.dispcapcnt.eva as u16,
-            self.dispcapcnt.evb as u16
+            eva,
+            evb
           );
           let new_b = process_channels(
             b_a as u16,
             b_b as u16,
             alpha_a as u16,
             alpha_b as u16,
-            self.dispcapcnt.eva as u16,
-            self.dispcapcnt.evb as u16
+            eva,
+            evb
           );
           // Dest_Alpha = (SrcA_Alpha AND (EVA>0)) OR (SrcB_Alpha AND EVB>0))
           let alpha = (alpha_a > 0 && self.dispcapcnt.eva > 0) || (alpha_b > 0 && self.dispcapcnt.evb > 0);
 
-          let new_color = (new_r as u16) & 0x1f | ((new_g as u16) & 0x1f) << 5 | ((new_b as u16) & 0x1f) << 10 | (alpha as u16) << 15;
+          let new_color = new_r as u16 | (new_g as u16) << 5 | (new_b as u16) << 10 | (alpha as u16) << 15;
 
           if self.vram.lcdc.contains(&Bank::new(write_block)) {
             self.vram.banks[write_block][write_offset + 2 * index] = new_color as u8;
@@ -495,7 +533,7 @@ impl GPU {
         BANK_G => self.vram.map_bank(Bank::BankG, &self.vramcnt[offset as usize]),
         BANK_H => self.vram.map_bank(Bank::BankH, &self.vramcnt[offset as usize]),
         BANK_I => self.vram.map_bank(Bank::BankI, &self.vramcnt[offset as usize]),
-        _ => todo!("unimplemented")
+        _ => unreachable!("can't happen")
       }
     }
   }
@@ -519,8 +557,14 @@ impl GPU {
   }
 
   fn render_line(&mut self) {
+    self.engine3d.start_display_line(self.vcount, &self.vram);
+
+    if self.uses_display_fifo() {
+      self.display_fifo.start_line();
+    }
+
     if self.powcnt1.contains(PowerControlRegister1::ENGINE_A_ENABLE) {
-      self.engine_a.render_line(self.vcount, &mut self.vram, &self.engine3d.frame_buffer);
+      self.engine_a.render_line(self.vcount, &mut self.vram, &self.engine3d.frame_buffer, &self.display_fifo.line);
 
       // capture image if needed
       if self.is_capturing && self.vcount < self.dispcapcnt.get_capture_height() {
@@ -529,7 +573,7 @@ impl GPU {
       }
     }
     if self.powcnt1.contains(PowerControlRegister1::ENGINE_B_ENABLE) {
-      self.engine_b.render_line(self.vcount, &mut self.vram, &self.engine3d.frame_buffer);
+      self.engine_b.render_line(self.vcount, &mut self.vram, &self.engine3d.frame_buffer, &self.display_fifo.line);
     }
   }
 }
\ No newline at end of file
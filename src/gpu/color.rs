@@ -58,6 +58,20 @@ impl Color {
     *self
   }
 
+  /// Expands a color with 6 bits per channel to 8 bits per channel, which is only done for the final output.
+  pub fn to_rgb8(&mut self) -> Self {
+    self.r = (self.r << 2) | (self.r >> 4);
+    self.g = (self.g << 2) | (self.g >> 4);
+    self.b = (self.b << 2) | (self.b >> 4);
+
+    *self
+  }
+
+  /// Packs a color with 6 bits per channel back into 15 bits, like display capture does.
+  pub fn to_rgb15(&self) -> u16 {
+    (self.r >> 1) as u16 | ((self.g >> 1) as u16) << 5 | ((self.b >> 1) as u16) << 10
+  }
+
   pub fn to_rgb6(&mut self) -> Self {
     self.r = Self::upscale(self.r);
     self.g = Self::upscale(self.g);
@@ -118,6 +132,16 @@ impl Color {
     }
   }
 
+  /// Expands a 15 bit color to the 6 bits per channel that the 2D engines composite in.
+  pub fn to_rgb18(val: u16) -> Self {
+    Color {
+      r: ((val & 0x1f) << 1) as u8,
+      g: (((val >> 5) & 0x1f) << 1) as u8,
+      b: (((val >> 10) & 0x1f) << 1) as u8,
+      alpha: None
+    }
+  }
+
   pub fn from(val: u16) -> Self {
     Color {
       r: (val & 0x1f) as u8,
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::SCREEN_WIDTH;
+
+/*
+  The main memory display FIFO (DISP_MMEM_FIFO). Games stream 15 bit pixels into it,
+  usually using DMAs with the main memory display start timing, and engine A shows them
+  in display mode 3. Display capture can also use it as source B.
+
+  The hardware FIFO is only 16 words deep and gets refilled while the line is drawn. Here
+  it holds up to a whole line, which gets taken out right before the line is rendered.
+*/
+#[derive(Serialize, Deserialize)]
+pub struct DisplayFifo {
+  fifo: VecDeque<u16>,
+  pub line: Box<[u16]>
+}
+
+impl DisplayFifo {
+  pub fn new() -> Self {
+    Self {
+      fifo: VecDeque::with_capacity(SCREEN_WIDTH as usize),
+      line: vec![0; SCREEN_WIDTH as usize].into_boxed_slice()
+    }
+  }
+
+  /// Every write contains two pixels, writes past a full line are lost.
+  pub fn write(&mut self, value: u32) {
+    for pixel in [value as u16, (value >> 16) as u16] {
+      if self.needs_data() {
+        self.fifo.push_back(pixel);
+      }
+    }
+  }
+
+  pub fn needs_data(&self) -> bool {
+    self.fifo.len() < SCREEN_WIDTH as usize
+  }
+
+  /// Moves the next line of pixels out of the FIFO. Pixels that never arrived show up as black.
+  pub fn start_line(&mut self) {
+    for pixel in self.line.iter_mut() {
+      *pixel = self.fifo.pop_front().unwrap_or(0);
+    }
+  }
+}
+
+impl Default for DisplayFifo {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::number::Number;
+use crate::{cpu::registers::mosaic_register::MosaicRegister, number::Number};
 
 use super::{
   color::Color, registers::{
@@ -33,7 +33,7 @@ struct OamAttributes {
   double_sized_flag: bool,
   obj_disable: bool,
   obj_mode: u16,
-  _obj_mosaic: bool,
+  obj_mosaic: bool,
   palette_flag: bool,
   obj_shape: u16,
   obj_size: u16,
@@ -70,7 +70,8 @@ pub struct ObjectPixel {
   pub priority: u16,
   pub color: Option<Color>,
   pub is_window: bool,
-  pub is_transparent: bool
+  pub is_transparent: bool,
+  pub is_mosaic: bool
 }
 
 impl ObjectPixel {
@@ -79,7 +80,8 @@ impl ObjectPixel {
       priority: 4,
       color: None,
       is_window: false,
-      is_transparent: false
+      is_transparent: false,
+      is_mosaic: false
     }
   }
 }
@@ -89,6 +91,8 @@ pub struct Engine2d<const IS_ENGINE_B: bool> {
   pub dispcnt: DisplayControlRegister<IS_ENGINE_B>,
   pub oam: Box<[u8]>,
   pub pixels: Box<[u8]>,
+  /// How many times larger than the native resolution `pixels` is in each direction.
+  resolution_scale: usize,
   pub winin: WindowInRegister,
   pub winout: WindowOutRegister,
   pub winh: [WindowHorizontalRegister; 2],
@@ -100,12 +104,21 @@ pub struct Engine2d<const IS_ENGINE_B: bool> {
   pub bgxofs: [u16; 4],
   pub bgyofs: [u16; 4],
   pub bg_props: [BgProps; 2],
+  pub mosaic: MosaicRegister,
+  /// Lines since the mosaic blocks last started, the hardware resets these at VBlank.
+  bg_mosaic_counter: u16,
+  obj_mosaic_counter: u16,
   bg_lines: [Box<[Option<Color>]>; 4],
   obj_lines: Box<[ObjectPixel]>,
   pub master_brightness: MasterBrightnessRegister,
   pub palette_ram: Box<[u8]>,
   pub debug_on: bool,
-  pub pixel_alphas: Box<[bool]>
+  pub pixel_alphas: Box<[bool]>,
+  /// The current line at the native resolution before master brightness, which is what display capture sees.
+  pub capture_line: Box<[Color]>,
+  /// The current line of the 3D layer at the internal resolution, empty when it isn't upscaled.
+  #[serde(skip)]
+  upscaled_3d_line: Vec<Option<Color>>
 }
 
 impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
@@ -113,11 +126,16 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     Self {
       dispcnt: DisplayControlRegister::new(),
       pixel_alphas: vec![false; SCREEN_WIDTH as usize].into_boxed_slice(),
+      capture_line: vec![Color::new(); SCREEN_WIDTH as usize].into_boxed_slice(),
       oam: vec![0; 0x400].into_boxed_slice(),
       pixels: vec![0; 4 * SCREEN_HEIGHT  as usize * SCREEN_WIDTH as usize].into_boxed_slice(),
+      resolution_scale: 1,
       bgxofs: [0; 4],
       bgyofs: [0; 4],
       bg_props: [BgProps::new(); 2],
+      mosaic: MosaicRegister::new(),
+      bg_mosaic_counter: 0,
+      obj_mosaic_counter: 0,
       winh: [WindowHorizontalRegister::new(); 2],
       winv: [WindowVerticalRegister::new(); 2],
       winin: WindowInRegister::from_bits_retain(0),
@@ -130,7 +148,19 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       master_brightness: MasterBrightnessRegister::new(),
       palette_ram: vec![0; 0x400].into_boxed_slice(),
       obj_lines: vec![ObjectPixel::new(); SCREEN_WIDTH as usize].into_boxed_slice(),
-      debug_on: false
+      debug_on: false,
+      upscaled_3d_line: Vec::new()
+    }
+  }
+
+  pub fn resolution_scale(&self) -> usize {
+    self.resolution_scale
+  }
+
+  pub fn set_resolution_scale(&mut self, scale: usize) {
+    if scale != self.resolution_scale {
+      self.resolution_scale = scale;
+      self.pixels = vec![0; 4 * SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize * scale * scale].into_boxed_slice();
     }
   }
 
@@ -199,7 +229,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       0x46 => self.winv[1].val,
       0x48 => self.winin.bits(),
       0x4a => self.winout.bits(),
-      0x4c => 0, // TODO
+      0x4c => 0, // mosaic is write only
       0x50 => self.bldcnt.value,
       0x52 => self.bldalpha.read(),
       0x54 => self.bldy.read(),
@@ -273,7 +303,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       0x46 => self.winv[1].write(value),
       0x48 => self.winin = WindowInRegister::from_bits_retain(value),
       0x4a => self.winout = WindowOutRegister::from_bits_retain(value),
-      0x4c..=0x4e => (), // TODO
+      0x4c => self.mosaic.write(val, mask.unwrap_or(0)),
+      0x4e => (),
       0x50 => self.bldcnt.write(value),
       0x52 => self.bldalpha.write(value),
       0x54 => self.bldy.write(value),
@@ -145,6 +145,52 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
   }
 
   fn finalize_pixel(&mut self, x: u16, y: u16, sorted_layers: &Vec<usize>, window_type: WindowType) {
+    if self.upscaled_3d_line.is_empty() {
+      let color = self.compose_pixel(x, sorted_layers, &window_type);
+
+      self.capture_line[x as usize] = color;
+
+      self.set_pixel(x as usize, y as usize, self.master_brightness.apply_effect(color));
+
+      return;
+    }
+
+    // the 3D layer has more detail than the others, so every one of its pixels gets composited separately
+    let scale = self.resolution_scale();
+    let width = SCREEN_WIDTH as usize * scale;
+
+    let mut sum = [0u32; 3];
+
+    for sub_y in 0..scale {
+      for sub_x in 0..scale {
+        let scaled_x = x as usize * scale + sub_x;
+
+        self.bg_lines[0][x as usize] = self.upscaled_3d_line[scaled_x + sub_y * width];
+
+        let color = self.compose_pixel(x, sorted_layers, &window_type);
+
+        sum[0] += color.r as u32;
+        sum[1] += color.g as u32;
+        sum[2] += color.b as u32;
+
+        self.set_scaled_pixel(scaled_x, y as usize * scale + sub_y, self.master_brightness.apply_effect(color));
+      }
+    }
+
+    // capture sees the native resolution pixel
+    let count = (scale * scale) as u32;
+
+    self.capture_line[x as usize] = Color {
+      r: (sum[0] / count) as u8,
+      g: (sum[1] / count) as u8,
+      b: (sum[2] / count) as u8,
+      alpha: None
+    };
+  }
+
+  /// Picks the visible layer for the pixel and applies color effects. Returns the color before
+  /// master brightness, with 6 bits per channel.
+  fn compose_pixel(&mut self, x: u16, sorted_layers: &Vec<usize>, window_type: &WindowType) -> Color {
     let mut bottom_layer: Option<Layer> = None;
     let mut top_layer: Option<Layer> = None;
 
@@ -161,7 +207,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
 
     let obj_layer = Layer::new(4, self.obj_lines[x as usize].priority as usize);
 
-    if self.dispcnt.flags.contains(DisplayControlRegisterFlags::DISPLAY_OBJ) && self.display_window_obj(&window_type) {
+    if self.dispcnt.flags.contains(DisplayControlRegisterFlags::DISPLAY_OBJ) && self.display_window_obj(window_type) {
       if top_layer.is_none() || obj_layer.priority <= top_layer.unwrap().priority {
         bottom_layer = top_layer;
         top_layer = Some(obj_layer);
@@ -190,7 +236,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       (None, None)
     };
 
-    let default_color = Color::to_rgb24((self.palette_ram[0] as u16) | (self.palette_ram[1] as u16) << 8);
+    let default_color = Color::to_rgb18((self.palette_ram[0] as u16) | (self.palette_ram[1] as u16) << 8);
 
     if let Some(mut top_layer_color) = top_layer_color {
       // this is safe to do, as we've verified the top layer and color above
@@ -205,26 +251,26 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
             top_layer_color = self.blend_colors(top_layer_color, color2, self.bldalpha.eva as u16, self.bldalpha.evb as u16);
           }
         }
-      } else if self.bldcnt.bg_first_pixels[top_layer.index] && self.should_apply_effects(&window_type) {
+      } else if top_layer.index == 0 && self.is_bg0_3d() && self.is_bottom_layer_blended(bottom_layer) {
+        top_layer_color = self.blend_3d_pixel(x as usize, top_layer_color, bottom_layer.unwrap());
+      } else if self.bldcnt.bg_first_pixels[top_layer.index] && self.should_apply_effects(window_type) {
         top_layer_color = self.process_pixel(x as usize, top_layer_color, bottom_layer);
       }
 
-      // lastly apply master brightness
-      top_layer_color = self.master_brightness.apply_effect(top_layer_color);
-
       self.pixel_alphas[x as usize] = true;
-      self.set_pixel(x as usize, y as usize, top_layer_color.convert());
+
+      top_layer_color
     } else {
       self.pixel_alphas[x as usize] = false;
-      self.set_pixel(x as usize, y as usize, default_color);
-    }
 
+      default_color
+    }
   }
 
   fn blend_colors(&self, color: Color, color2: Color, eva: u16, evb: u16) -> Color {
-    let r = cmp::min(31, (color.r as u16 * eva + color2.r as u16 * evb) >> 4) as u8;
-    let g = cmp::min(31, (color.g as u16 * eva + color2.g as u16 * evb) >> 4) as u8;
-    let b = cmp::min(31, (color.b as u16 * eva + color2.b as u16 * evb) >> 4) as u8;
+    let r = cmp::min(63, (color.r as u16 * eva + color2.r as u16 * evb) >> 4) as u8;
+    let g = cmp::min(63, (color.g as u16 * eva + color2.g as u16 * evb) >> 4) as u8;
+    let b = cmp::min(63, (color.b as u16 * eva + color2.b as u16 * evb) >> 4) as u8;
 
     Color {
       r,
@@ -234,6 +280,25 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     }
   }
 
+  /// The 3D layer blends with any second target below it using the alpha of its own pixels,
+  /// no matter which color effect is selected, whether BG0 is a first target, or the windows.
+  fn blend_3d_pixel(&self, x: usize, color: Color, bottom_layer: Layer) -> Color {
+    let color2 = if bottom_layer.index < 4 {
+      self.bg_lines[bottom_layer.index][x]
+    } else {
+      self.obj_lines[x].color
+    };
+
+    let Some(color2) = color2 else {
+      return color;
+    };
+
+    let eva = color.alpha.unwrap_or(0x1f) + 1;
+    let evb = 32 - eva;
+
+    Engine3d::blend_colors3d(color, color2, eva as u16, evb as u16)
+  }
+
   fn process_pixel(&mut self, x: usize, color: Color, bottom_layer: Option<Layer>) -> Color {
     match self.bldcnt.color_effect {
       ColorEffect::AlphaBlending => {
@@ -245,16 +310,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
 
         if let Some(blend_layer) = layer {
           if blend_layer.index != 4 {
-            if let Some(mut color2) = self.bg_lines[blend_layer.index][x] {
-              if let Some(alpha) = color.alpha {
-                let eva = alpha + 1;
-                let evb = 32 - eva;
-                let mut color = color;
-
-                Engine3d::blend_colors3d(color.to_rgb6(), color2.to_rgb6(), eva as u16, evb as u16).to_rgb5()
-              } else {
-                self.blend_colors(color, color2, self.bldalpha.eva as u16, self.bldalpha.evb as u16)
-              }
+            if let Some(color2) = self.bg_lines[blend_layer.index][x] {
+              self.blend_colors(color, color2, self.bldalpha.eva as u16, self.bldalpha.evb as u16)
             } else {
               color
             }
@@ -271,14 +328,14 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
         }
       }
       ColorEffect::Brighten => {
-          let white = Color {
-            r: 0xff,
-            g: 0xff,
-            b: 0xff,
-            alpha: None
-          };
-          self.blend_colors(color, white, (16 - self.bldy.evy) as u16, self.bldy.evy as u16)
+        let white = Color {
+          r: 0x3f,
+          g: 0x3f,
+          b: 0x3f,
+          alpha: None
+        };
 
+        self.blend_colors(color, white, (16 - self.bldy.evy) as u16, self.bldy.evy as u16)
       }
       ColorEffect::Darken => {
         let black = Color {
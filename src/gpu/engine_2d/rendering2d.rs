@@ -1,5 +1,5 @@
 use crate::gpu::
-{engine_3d::Pixel3d, registers::
+{engine_3d::{upscaling, Pixel3d}, registers::
   {
     bg_control_register::BgControlRegister,
     display_control_register::
@@ -40,6 +40,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       return;
     }
 
+    let y = self.get_obj_mosaic_y(&obj_attributes, y, y_coordinate);
+
     let tile_number = obj_attributes.tile_number;
 
     let palette_bank = if !obj_attributes.palette_flag {
@@ -113,7 +115,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
               priority: obj_attributes.priority,
               color,
               is_window: obj_attributes.obj_mode == 2,
-              is_transparent: obj_attributes.obj_mode == 1
+              is_transparent: obj_attributes.obj_mode == 1,
+              is_mosaic: obj_attributes.obj_mosaic
             }
           }
         }
@@ -126,8 +129,10 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       self.render_objects(y, vram);
     }
 
+    let is_bg0_3d = self.is_bg0_3d();
+
     if self.bg_mode_enabled(0) {
-      if !IS_ENGINE_B && (self.dispcnt.bg_mode == BgMode::Mode6 || self.dispcnt.flags.contains(DisplayControlRegisterFlags::BG_3D_SELECTION)) {
+      if is_bg0_3d {
         self.render_3d_line(y, frame_buffer);
       } else {
         self.render_text_line(0, y, vram);
@@ -209,9 +214,67 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       }
     }
 
+    for i in 0..4 {
+      // the 3D layer is never affected by mosaic
+      if self.bg_mode_enabled(i) && self.bgcnt[i].contains(BgControlRegister::MOSAIC) && !(i == 0 && is_bg0_3d) {
+        self.apply_bg_mosaic(i);
+      }
+    }
+
     self.finalize_scanline(y);
   }
 
+  fn apply_bg_mosaic(&mut self, bg_index: usize) {
+    let (width, _) = self.mosaic.bg_size();
+
+    if width == 1 {
+      return;
+    }
+
+    for x in 0..SCREEN_WIDTH as usize {
+      self.bg_lines[bg_index][x] = self.bg_lines[bg_index][x - x % width as usize];
+    }
+  }
+
+  /// Pixels of mosaic objects repeat the pixel at the start of their mosaic block. The blocks
+  /// are laid out across the whole line rather than per object, so that pixel can belong to
+  /// a different object, or be empty.
+  fn apply_obj_mosaic(&mut self) {
+    let (width, _) = self.mosaic.obj_size();
+
+    if width == 1 {
+      return;
+    }
+
+    let mut block_pixel = self.obj_lines[0];
+
+    for x in 0..SCREEN_WIDTH as usize {
+      if x % width as usize == 0 {
+        block_pixel = self.obj_lines[x];
+      } else if self.obj_lines[x].is_mosaic {
+        self.obj_lines[x] = block_pixel;
+      }
+    }
+  }
+
+  /// Returns the line of the object that's drawn on line `y`, which stays the same for the
+  /// height of a mosaic block. Lines above the top of the object show its first line.
+  fn get_obj_mosaic_y(&self, obj_attributes: &OamAttributes, y: u16, y_coordinate: i16) -> u16 {
+    if !obj_attributes.obj_mosaic {
+      return y;
+    }
+
+    ((y - self.obj_mosaic_counter) as i16).max(y_coordinate) as u16
+  }
+
+  fn step_mosaic_counters(&mut self) {
+    let (_, bg_height) = self.mosaic.bg_size();
+    let (_, obj_height) = self.mosaic.obj_size();
+
+    self.bg_mosaic_counter = if self.bg_mosaic_counter + 1 >= bg_height { 0 } else { self.bg_mosaic_counter + 1 };
+    self.obj_mosaic_counter = if self.obj_mosaic_counter + 1 >= obj_height { 0 } else { self.obj_mosaic_counter + 1 };
+  }
+
   fn render_extended_line(&mut self, bg_index: usize, vram: &VRam) {
     if self.bgcnt[bg_index].contains(BgControlRegister::PALETTES) {
       if self.bgcnt[bg_index].character_base_block() & 0b1 != 0 {
@@ -227,12 +290,55 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     }
   }
 
+  /// Whether BG0 shows the output of the 3D engine instead of a 2D background.
+  pub fn is_bg0_3d(&self) -> bool {
+    !IS_ENGINE_B && (self.dispcnt.bg_mode == BgMode::Mode6 || self.dispcnt.flags.contains(DisplayControlRegisterFlags::BG_3D_SELECTION))
+  }
+
+  /// Copies a line of the 3D frame buffer into BG0. The layer can only be scrolled horizontally
+  /// using BG0HOFS, and whatever gets scrolled off of the 3D frame is transparent, as are pixels
+  /// with an alpha of 0.
   fn render_3d_line(&mut self, y: u16, frame_buffer: &[Pixel3d]) {
+    let scale = self.resolution_scale();
 
-    for x in 0..SCREEN_WIDTH {
-      let pixel = frame_buffer[(x + y * SCREEN_WIDTH) as usize];
+    // the 3D frame buffer can briefly be at a different scale than this engine right after the setting changes
+    let frame_scale = upscaling::frame_scale(frame_buffer.len());
+    let frame_width = SCREEN_WIDTH as usize * frame_scale;
+
+    let x_offset = self.bgxofs[0] as usize;
 
-      self.bg_lines[0][x as usize] = pixel.color;
+    let get_color = |frame_x: usize, frame_y: usize| {
+      frame_buffer[frame_x + frame_y * frame_width]
+        .color
+        .filter(|color| color.alpha != Some(0))
+    };
+
+    for x in 0..SCREEN_WIDTH as usize {
+      let source_x = (x + x_offset) & 0x1ff;
+
+      self.bg_lines[0][x] = if source_x < SCREEN_WIDTH as usize {
+        get_color(source_x * frame_scale, y as usize * frame_scale)
+      } else {
+        None
+      };
+    }
+
+    if scale > 1 {
+      for sub_y in 0..scale {
+        let frame_y = (y as usize * scale + sub_y) * frame_scale / scale;
+
+        for x in 0..SCREEN_WIDTH as usize * scale {
+          let source_x = (x / scale + x_offset) & 0x1ff;
+
+          let color = if source_x < SCREEN_WIDTH as usize {
+            get_color((source_x * scale + x % scale) * frame_scale / scale, frame_y)
+          } else {
+            None
+          };
+
+          self.upscaled_3d_line.push(color);
+        }
+      }
     }
   }
 
@@ -249,6 +355,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
         self.render_normal_object(obj_attributes, y, vram);
       }
     }
+
+    self.apply_obj_mosaic();
   }
 
   fn get_obj_affine_params(&self, affine_index: u16) -> (i16, i16, i16, i16) {
@@ -301,7 +409,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       double_sized_flag,
       obj_disable,
       obj_mode,
-      _obj_mosaic: obj_mosaic,
+      obj_mosaic,
       palette_flag,
       obj_shape,
       x_coordinate,
@@ -336,6 +444,12 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
      */
     let (tilemap_base, tile_base) = self.get_tile_base_addresses(bg_index);
 
+    let y = if self.bgcnt[bg_index].contains(BgControlRegister::MOSAIC) {
+      y - self.bg_mosaic_counter
+    } else {
+      y
+    };
+
     let mut x = 0;
 
     let (background_width, background_height) = self.bgcnt[bg_index].get_screen_dimensions();
@@ -443,7 +557,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     if value == COLOR_TRANSPARENT {
       None
     } else {
-      Some(Color::from(value))
+      Some(Color::to_rgb18(value))
     }
   }
 
@@ -463,7 +577,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     };
 
     if color_raw != COLOR_TRANSPARENT && palette_index != 0 {
-      Some(Color::from(color_raw))
+      Some(Color::to_rgb18(color_raw))
     } else {
       None
     }
@@ -487,7 +601,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     };
 
     if color != COLOR_TRANSPARENT && index != 0 {
-      Some(Color::from(color))
+      Some(Color::to_rgb18(color))
     } else {
       None
     }
@@ -607,14 +721,15 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     let color = if color_raw == 0 {
       None
     } else {
-      Some(Color::from(color_raw))
+      Some(Color::to_rgb18(color_raw))
     };
 
     self.obj_lines[x] = ObjectPixel {
       priority: obj_attributes.priority,
       color,
       is_window: obj_attributes.obj_mode == 2,
-      is_transparent: obj_attributes.obj_mode == 1
+      is_transparent: obj_attributes.obj_mode == 1,
+      is_mosaic: obj_attributes.obj_mosaic
     };
   }
 
@@ -623,12 +738,12 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
 
     let (x_coordinate, y_coordinate) = self.get_obj_coordinates(obj_attributes.x_coordinate, obj_attributes.y_coordinate);
 
-    let y_pos_in_sprite = y as i16 - y_coordinate;
-
-    if y_pos_in_sprite < 0 || y_pos_in_sprite as u32 >= obj_height {
+    if (y as i16) < y_coordinate || (y as i16 - y_coordinate) as u32 >= obj_height {
       return;
     }
 
+    let y_pos_in_sprite = self.get_obj_mosaic_y(&obj_attributes, y, y_coordinate) as i16 - y_coordinate;
+
     let tile_number = obj_attributes.tile_number;
 
     let bit_depth = if obj_attributes.palette_flag {
@@ -701,20 +816,23 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
             priority: obj_attributes.priority,
             color,
             is_window: obj_attributes.obj_mode == 2,
-            is_transparent: obj_attributes.obj_mode == 1
+            is_transparent: obj_attributes.obj_mode == 1,
+            is_mosaic: obj_attributes.obj_mosaic
           };
         }
       }
     }
   }
 
-  pub fn render_line(&mut self, y: u16, vram: &mut VRam, frame_buffer: &[Pixel3d]) {
+  pub fn render_line(&mut self, y: u16, vram: &mut VRam, frame_buffer: &[Pixel3d], fifo_line: &[u16]) {
+    self.upscaled_3d_line.clear();
+
     match self.dispcnt.display_mode {
       DisplayMode::Mode0 => {
         let color = Color {
-          r: 0xff,
-          g: 0xff,
-          b: 0xff,
+          r: 0x3f,
+          g: 0x3f,
+          b: 0x3f,
           alpha: None
         };
 
@@ -730,21 +848,43 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
 
           let color = bank[index] as u16 | (bank[(index + 1) as usize] as u16) << 8;
 
-          let color = Color::to_rgb24(color);
+          let color = self.master_brightness.apply_effect(Color::to_rgb18(color));
+
+          self.set_pixel(x as usize, y as usize, color);
+        }
+      }
+      DisplayMode::Mode3 => {
+        for x in 0..SCREEN_WIDTH {
+          let color = self.master_brightness.apply_effect(Color::to_rgb18(fifo_line[x as usize]));
 
           self.set_pixel(x as usize, y as usize, color);
         }
       }
-      DisplayMode::Mode3 => todo!()
     }
+
+    self.step_mosaic_counters();
   }
 
   fn oam_read_16(&self, address: usize) -> u16 {
     (self.oam[address] as u16) | (self.oam[address + 1] as u16) << 8
   }
 
+  /// Sets a native resolution pixel, which covers a block of pixels when the output is upscaled.
+  /// Colors have 6 bits per channel until they get written to the output.
   pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
-    let i = 4 * (x + y * SCREEN_WIDTH as usize);
+    let scale = self.resolution_scale();
+
+    for sub_y in 0..scale {
+      for sub_x in 0..scale {
+        self.set_scaled_pixel(x * scale + sub_x, y * scale + sub_y, color);
+      }
+    }
+  }
+
+  pub fn set_scaled_pixel(&mut self, x: usize, y: usize, mut color: Color) {
+    let i = 4 * (x + y * SCREEN_WIDTH as usize * self.resolution_scale());
+
+    color.to_rgb8();
 
     self.pixels[i] = color.r;
     self.pixels[i + 1] = color.g;
@@ -794,6 +934,14 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
     self.bg_props[bg_index - 2].internal_x += self.bg_props[bg_index - 2].dmx as i32;
     self.bg_props[bg_index - 2].internal_y += self.bg_props[bg_index - 2].dmy as i32;
 
+    // vertical mosaic keeps using the reference point from the first line of the block
+    if self.bgcnt[bg_index].contains(BgControlRegister::MOSAIC) {
+      let lines = self.bg_mosaic_counter as i32;
+
+      ref_x -= self.bg_props[bg_index - 2].dmx as i32 * lines;
+      ref_y -= self.bg_props[bg_index - 2].dmy as i32 * lines;
+    }
+
     for x in 0..SCREEN_WIDTH {
       let mut transformed_x = ref_x >> 8;
       let mut transformed_y = ref_y >> 8;
@@ -873,7 +1021,7 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
           if color_raw == 0 {
             None
           } else {
-            Some(Color::from(color_raw))
+            Some(Color::to_rgb18(color_raw))
           }
         }
         AffineType::Extended8bpp => {
@@ -907,5 +1055,8 @@ impl<const IS_ENGINE_B: bool> Engine2d<IS_ENGINE_B> {
       bg_prop.internal_x = bg_prop.x;
       bg_prop.internal_y = bg_prop.y;
     }
+
+    self.bg_mosaic_counter = 0;
+    self.obj_mosaic_counter = 0;
   }
 }
\ No newline at end of file
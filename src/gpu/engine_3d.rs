@@ -1,15 +1,19 @@
-use std::collections::{HashSet, VecDeque};
+use std::{collections::{HashMap, HashSet, VecDeque}, mem, ops::Range, sync::Arc};
 
 use box_test::BoxTest;
+use command_log::{CommandLog, GxDump};
 use diffuse_color::DiffuseColor;
 use light::Light;
 use matrix::Matrix;
 use polygon::Polygon;
 use polygon_attributes::PolygonAttributes;
+use render_thread::RenderThread;
 use rendering_attributes::RenderingAttributes;
+use scanline_rendering::ScanlineFrame;
 use serde::{Deserialize, Serialize};
 use specular_color::SpecularColor;
 use texcoord::Texcoord;
+use texture_cache::{DecodedTexture, ReplacementTexture, TextureKey};
 use texture_params::{TextureParams, TransformationMode};
 use vertex::Vertex;
 use viewport::Viewport;
@@ -18,6 +22,7 @@ use crate::cpu::registers::interrupt_request_register::InterruptRequestRegister;
 
 use super::{
   color::Color,
+  vram::VRam,
   registers::{
     clear_color_register::ClearColorRegister,
     display_3d_control_register::Display3dControlRegister,
@@ -41,6 +46,16 @@ pub mod texcoord;
 pub mod polygon;
 pub mod box_test;
 pub mod rendering_attributes;
+pub mod command_log;
+pub mod interpolator;
+pub mod post_processing;
+pub mod render_thread;
+pub mod slope;
+pub mod texture_cache;
+pub mod upscaling;
+pub mod scanline_rendering;
+#[cfg(test)]
+pub mod test_scene;
 
 pub const FIFO_CAPACITY: usize = 256;
 pub const POLYGON_BUFFER_SIZE: usize = 2048;
@@ -50,7 +65,9 @@ pub const VERTEX_BUFFER_SIZE: usize = 6144;
 pub struct Pixel3d {
   pub color: Option<Color>,
   pub depth: u32,
-  pub alpha: u8
+  pub alpha: u8,
+  // color that was underneath the topmost opaque polygon, edges are anti-aliased against it
+  pub below: Option<Color>
 }
 
 impl Pixel3d {
@@ -58,7 +75,8 @@ impl Pixel3d {
     Self {
       color: None,
       depth: 0,
-      alpha: 0
+      alpha: 0,
+      below: None
     }
   }
 }
@@ -224,11 +242,17 @@ impl Command {
       0x540 => SwapBuffers,
       0x580 => Viewport,
       0x5c0 => BoxTest,
+      0x5c4 => PosTest,
       0x5c8 => VecTest,
       _ => panic!("invalid address given to Command::from_address: {:x}", address)
     }
   }
 
+  pub fn is_test(&self) -> bool {
+    use Command::*;
+    matches!(*self, BoxTest | PosTest | VecTest)
+  }
+
   pub fn get_num_params(&self) -> usize {
     use Command::*;
     match *self {
@@ -276,8 +300,8 @@ impl Command {
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct GeometryCommandEntry {
-  command: Command,
-  param: u32
+  pub command: Command,
+  pub param: u32
 }
 
 impl GeometryCommandEntry {
@@ -343,7 +367,8 @@ pub struct Engine3d {
   emission: Color,
   lights: [Light; 4],
   vertex_color: Color,
-  vec_result: [i32; 4],
+  pos_result: [i32; 4],
+  vec_result: [i16; 3],
   primitive_type: PrimitiveType,
   current_vertices: Vec<Vertex>,
   translation_vector: [i32; 3],
@@ -354,6 +379,10 @@ pub struct Engine3d {
   clip_mtx_recalculate: bool,
   clip_matrix: Matrix,
   vertices_buffer: Vec<Vertex>,
+  // how many vertices are in vertex RAM, strips only store the vertices they share once
+  vertex_ram_count: usize,
+  // vertices the next polygon of a strip can reuse from the one before it
+  shared_vertices: usize,
   polygon_buffer: Vec<Polygon>,
   scale_vector: [i32; 3],
   pub frame_buffer: Box<[Pixel3d]>,
@@ -364,7 +393,34 @@ pub struct Engine3d {
   pub disp3dcnt: Display3dControlRegister,
   pub debug_on: bool,
   box_test: BoxTest,
-  pub found: HashSet<String>
+  pub found: HashSet<String>,
+  #[serde(skip)]
+  command_log: Option<CommandLog>,
+  #[serde(skip)]
+  log_requested: bool,
+  #[serde(skip)]
+  gx_dump: Option<GxDump>,
+  #[serde(skip)]
+  pub capture_textures: bool,
+  #[serde(skip)]
+  pub frame_textures: Vec<DecodedTexture>,
+  #[serde(skip)]
+  texture_replacements: Arc<HashMap<u64, ReplacementTexture>>,
+  #[serde(skip)]
+  texture_hashes: HashMap<TextureKey, u64>,
+  #[serde(skip)]
+  texture_hashes_generation: u32,
+  #[serde(skip)]
+  render_thread: Option<RenderThread>,
+  /// Enhancement that lifts the hardware's polygon and vertex RAM limits.
+  #[serde(skip)]
+  pub unlimited_polygons: bool,
+  rdlines_count: u8,
+  #[serde(skip)]
+  scanline_frame: Option<ScanlineFrame>,
+  /// Renders frames a few lines at a time, following the hardware's timing, instead of all at once.
+  #[serde(skip)]
+  pub scanline_rendering: bool
 }
 
 impl Engine3d {
@@ -415,7 +471,8 @@ impl Engine3d {
       specular_reflection: SpecularColor::new(),
       lights: [Light::new(); 4],
       vertex_color: Color::new(),
-      vec_result: [0; 4],
+      pos_result: [0; 4],
+      vec_result: [0; 3],
       primitive_type: PrimitiveType::Triangles,
       current_vertices: Vec::new(),
       translation_vector: [0; 3],
@@ -427,6 +484,8 @@ impl Engine3d {
       clip_mtx_recalculate: false,
       clip_matrix: Matrix::new(),
       vertices_buffer: Vec::new(),
+      vertex_ram_count: 0,
+      shared_vertices: 0,
       polygon_buffer: Vec::new(),
       frame_buffer: vec![Pixel3d::new(); SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize].into_boxed_slice(),
       attributes_buffer: vec![RenderingAttributes::new(); SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize].into_boxed_slice(),
@@ -436,8 +495,73 @@ impl Engine3d {
       disp3dcnt: Display3dControlRegister::from_bits_retain(0),
       debug_on: false,
       box_test: BoxTest::new(),
-      found: HashSet::new()
+      found: HashSet::new(),
+      command_log: None,
+      log_requested: false,
+      gx_dump: None,
+      capture_textures: false,
+      frame_textures: Vec::new(),
+      texture_replacements: Arc::new(HashMap::new()),
+      texture_hashes: HashMap::new(),
+      texture_hashes_generation: 0,
+      render_thread: None,
+      unlimited_polygons: false,
+      rdlines_count: 46,
+      scanline_frame: None,
+      scanline_rendering: false
+    }
+  }
+
+  /// Requests that the geometry commands of the next full frame be recorded. Once that
+  /// frame has been rendered, the dump can be retrieved with `take_gx_dump`.
+  pub fn start_command_log(&mut self) {
+    self.log_requested = true;
+  }
+
+  pub fn take_gx_dump(&mut self) -> Option<GxDump> {
+    self.gx_dump.take()
+  }
+
+  fn duplicate(&self) -> Self {
+    bincode::deserialize(&bincode::serialize(self).unwrap()).unwrap()
+  }
+
+  fn update_command_log(&mut self, vram: &VRam) {
+    if self.command_log.as_ref().is_some_and(|log| log.finished) {
+      let log = self.command_log.take().unwrap();
+
+      let mut engine: Box<Engine3d> = bincode::deserialize(&log.engine).unwrap();
+
+      // rendering registers are written through regular io rather than the fifo,
+      // so use the values that were actually in effect when the frame was rendered
+      engine.copy_rendering_registers(self);
+
+      self.gx_dump = Some(GxDump {
+        engine,
+        commands: log.commands,
+        vram: vram.clone()
+      });
     }
+
+    if self.log_requested {
+      self.log_requested = false;
+
+      self.command_log = Some(CommandLog::new(bincode::serialize(self).unwrap()));
+    }
+  }
+
+  fn copy_rendering_registers(&mut self, other: &Engine3d) {
+    self.disp3dcnt = Display3dControlRegister::from_bits_retain(other.disp3dcnt.bits());
+    self.clear_color = other.clear_color;
+    self.clear_depth = other.clear_depth;
+    self.clear_offset_x = other.clear_offset_x;
+    self.clear_offset_y = other.clear_offset_y;
+    self.fog_color = other.fog_color;
+    self.fog_offset = other.fog_offset;
+    self.fog_table = other.fog_table;
+    self.edge_colors = other.edge_colors;
+    self.toon_table = other.toon_table;
+    self.alpha_ref = other.alpha_ref;
   }
 
   pub fn clear_frame_buffer(&mut self) {
@@ -447,14 +571,62 @@ impl Engine3d {
     }
   }
 
-  pub fn clear_attributes_buffer(&mut self) {
-    for attr in self.attributes_buffer.iter_mut() {
+  /// Clears the rows using the images in texture slots 2 (color) and 3 (depth) instead of
+  /// the clear color and depth registers. Both are 256x256 and wrap around when scrolled.
+  pub fn clear_rear_plane_bitmap(&mut self, rows: Range<u32>, vram: &VRam) {
+    let scale = self.resolution_scale() as u32;
+
+    let width = SCREEN_WIDTH as u32 * scale;
+
+    for y in rows {
+      for x in 0..width {
+        let bitmap_x = (x / scale + self.clear_offset_x as u32) & 0xff;
+        let bitmap_y = (y / scale + self.clear_offset_y as u32) & 0xff;
+
+        let offset = 2 * (bitmap_x + bitmap_y * 256);
+
+        let color = vram.read_texture::<u16>(0x4_0000 + offset);
+        let depth = vram.read_texture::<u16>(0x6_0000 + offset);
+
+        let index = (x + y * width) as usize;
+
+        let pixel = &mut self.frame_buffer[index];
+
+        *pixel = Pixel3d::new();
+
+        let alpha = if color >> 15 == 1 { 0x1f } else { 0 };
+
+        if alpha != 0 {
+          let mut clear_color = Color::from(color).to_rgb6();
+
+          clear_color.alpha = Some(alpha);
+
+          pixel.color = Some(clear_color);
+        }
+
+        pixel.depth = (depth as u32 & 0x7fff) * 0x200 + 0x1ff;
+
+        self.attributes_buffer[index].fog_enabled = depth >> 15 == 1;
+      }
+    }
+  }
+
+  pub fn clear_attributes_buffer(&mut self, rows: Range<u32>) {
+    let width = SCREEN_WIDTH as usize * self.resolution_scale();
+
+    for attr in self.attributes_buffer[rows.start as usize * width..rows.end as usize * width].iter_mut() {
       *attr = RenderingAttributes::new();
+      attr.polygon_id = self.clear_color.clear_polygon_id as u32 & 0x3f;
+      attr.fog_enabled = self.clear_color.fog;
     }
   }
 
   pub fn read_ram_count(&self) -> u32 {
-    self.polygon_buffer.len() as u32 | (self.vertices_buffer.len() as u32) << 16
+    // the counts never go past the RAM limits, even when those limits aren't being enforced
+    let polygons = self.polygon_buffer.len().min(POLYGON_BUFFER_SIZE) as u32;
+    let vertices = self.vertex_ram_count.min(VERTEX_BUFFER_SIZE) as u32;
+
+    polygons | vertices << 16
   }
 
   pub fn read_clip_matrix(&mut self, address: u32) -> u32 {
@@ -470,6 +642,19 @@ impl Engine3d {
     self.clip_matrix.0[row as usize][column as usize] as u32
   }
 
+  pub fn read_position_test(&self, address: u32) -> u32 {
+    let index = (address - 0x400_0620) / 4;
+
+    self.pos_result[index as usize] as u32
+  }
+
+  pub fn read_vector_test(&self, address: u32) -> u16 {
+    let index = (address - 0x400_0630) / 2;
+
+    // 0x400_0636 is unused and always reads back as 0
+    self.vec_result.get(index as usize).map_or(0, |value| *value as u16)
+  }
+
   pub fn read_vector_matrix(&mut self, address: u32) -> u32 {
     let index = (address - 0x400_0680) / 4;
 
@@ -480,13 +665,27 @@ impl Engine3d {
   }
 
   pub fn read_geometry_status(&mut self, interrupt_request: &mut InterruptRequestRegister) -> u32 {
-    let value = self.gxstat.read(self.position_vector_sp as u32, self.projection_sp as u32, &self.fifo);
+    let value = self.peek_geometry_status();
 
     self.check_interrupts(interrupt_request);
 
     value
   }
 
+  /// Reads GXSTAT without the interrupt check a read from the CPU does.
+  pub fn peek_geometry_status(&self) -> u32 {
+    self.gxstat.read(self.position_vector_sp as u32, self.projection_sp as u32, &self.fifo)
+  }
+
+  pub fn write_disp3dcnt(&mut self, value: u16) {
+    let acknowledge_bits = Display3dControlRegister::COLOR_BUFFER_UNDERFLOW_ACKNOWLEDGE | Display3dControlRegister::POLYGON_OVERFLOW_ACKNOWLEDGE;
+
+    // the underflow/overflow flags are only set by hardware, and writing 1 acknowledges (clears) them
+    let flags = self.disp3dcnt.bits() & acknowledge_bits.bits() & !(value as u32);
+
+    self.disp3dcnt = Display3dControlRegister::from_bits_retain((value as u32 & !acknowledge_bits.bits()) | flags);
+  }
+
   pub fn write_alpha_ref(&mut self, value: u16) {
     self.alpha_ref = (value & 0x1f) as u8;
   }
@@ -587,6 +786,10 @@ impl Engine3d {
   }
 
   fn execute_command(&mut self, entry: GeometryCommandEntry) {
+    if let Some(log) = &mut self.command_log {
+      log.push(entry);
+    }
+
     if self.fifo.len() < FIFO_CAPACITY {
       self.gxstat.geometry_engine_busy = false;
     }
@@ -734,13 +937,54 @@ impl Engine3d {
         self.vertex_color.to_rgb6();
       }
       VecTest => {
-        let x = (entry.param as i16) << 6 >> 6;
-        let y = ((entry.param >> 4) as i16) >> 6;
-        let z = ((entry.param >> 14) as i16) >> 6;
+        // the vector is 1.0.9 fixed point, converted to 12 fractional bits before the multiplication
+        let x = ((entry.param as i16) << 6 >> 6) as i32;
+        let y = (((entry.param >> 4) as i16) >> 6) as i32;
+        let z = (((entry.param >> 14) as i16) >> 6) as i32;
 
-        let transformed = self.current_vector_matrix.multiply_row(&[x as i32, y as i32, z as i32, 0], 12);
+        let transformed = self.current_vector_matrix.multiply_row(&[x << 3, y << 3, z << 3, 0], 12);
+
+        // results are 4.12 fixed point, sign extended from bit 12
+        for (result, value) in self.vec_result.iter_mut().zip(transformed) {
+          *result = ((value as i16) << 3) >> 3;
+        }
+
+        self.gxstat.test_busy = self.fifo.iter().any(|entry| entry.command.is_test());
+      }
+      PosTest => {
+        if !self.command_started {
+          self.command_started = true;
+
+          self.command_params = PosTest.get_num_params();
+        }
+
+        self.command_params -= 1;
+
+        // the tested position also becomes the current vertex, same as with VTX_16
+        if self.command_params == 1 {
+          self.current_vertex.x = entry.param as i16;
+          self.current_vertex.y = (entry.param >> 16) as i16;
+        } else {
+          self.current_vertex.z = entry.param as i16;
+
+          self.command_started = false;
 
-        self.vec_result = transformed;
+          if self.clip_mtx_recalculate {
+            self.recalculate_clip_matrix();
+          }
+
+          self.pos_result = self.clip_matrix.multiply_row(
+            &[
+              self.current_vertex.x as i32,
+              self.current_vertex.y as i32,
+              self.current_vertex.z as i32,
+              0x1000
+            ],
+            12
+          );
+
+          self.gxstat.test_busy = self.fifo.iter().any(|entry| entry.command.is_test());
+        }
       }
       BeginVtxs => {
         self.primitive_type = PrimitiveType::from(entry.param & 0x3);
@@ -751,6 +995,7 @@ impl Engine3d {
         self.max_vertices = self.primitive_type.get_num_vertices();
 
         self.current_vertices.clear();
+        self.shared_vertices = 0;
       }
       MtxPush => {
         match self.matrix_mode {
@@ -1022,6 +1267,7 @@ impl Engine3d {
           }
 
           self.gxstat.box_test_result = self.box_test.do_test(self.clip_matrix);
+          self.gxstat.test_busy = self.fifo.iter().any(|entry| entry.command.is_test());
         }
       }
       _ => panic!("command not iplemented yet: {:?}", entry.command)
@@ -1156,11 +1402,18 @@ impl Engine3d {
     }
   }
 
+  fn is_polygon_ram_full(&self, new_vertices: usize) -> bool {
+    !self.unlimited_polygons && (
+      self.polygon_buffer.len() >= POLYGON_BUFFER_SIZE ||
+      self.vertex_ram_count + new_vertices > VERTEX_BUFFER_SIZE
+    )
+  }
+
   fn add_vertex(&mut self) {
     let vertex = self.current_vertex;
 
-    // TODO: check polygon ram overflow here
-    if self.polygon_buffer.len() == POLYGON_BUFFER_SIZE {
+    if self.is_polygon_ram_full(0) {
+      self.disp3dcnt.insert(Display3dControlRegister::POLYGON_OVERFLOW_ACKNOWLEDGE);
       return;
     }
 
@@ -1210,6 +1463,9 @@ impl Engine3d {
 
 
   fn submit_polygon(&mut self) {
+    // polygons that get dropped or clipped don't leave anything behind for the next one to share
+    let shared_vertices = mem::take(&mut self.shared_vertices);
+
     let a = (
       self.current_vertices[0].transformed[0] - self.current_vertices[1].transformed[0],
       self.current_vertices[0].transformed[1] - self.current_vertices[1].transformed[1],
@@ -1261,6 +1517,12 @@ impl Engine3d {
       }
     }
 
+    let clipped = self.current_vertices.iter().any(|vertex| {
+      let w = vertex.transformed[3];
+
+      vertex.transformed[..3].iter().any(|coordinate| *coordinate > w || *coordinate < -w)
+    });
+
     for i in (0..3).rev() {
       Self::sutherland_hodgman_clipping(i, &mut self.current_vertices);
     }
@@ -1269,6 +1531,25 @@ impl Engine3d {
       return;
     }
 
+    // clipping creates new vertices, so a clipped polygon stores all of them
+    let new_vertices = if clipped {
+      self.current_vertices.len()
+    } else {
+      self.current_vertices.len() - shared_vertices
+    };
+
+    // polygons that don't fit in polygon/vertex RAM are dropped entirely
+    if self.is_polygon_ram_full(new_vertices) {
+      self.disp3dcnt.insert(Display3dControlRegister::POLYGON_OVERFLOW_ACKNOWLEDGE);
+      return;
+    }
+
+    self.vertex_ram_count += new_vertices;
+
+    if !clipped && matches!(self.primitive_type, PrimitiveType::TriangleStrips | PrimitiveType::QuadStrips) {
+      self.shared_vertices = 2;
+    }
+
     let mut polygon = Polygon {
       palette_base: self.palette_base as usize,
       start: self.vertices_buffer.len(),
@@ -1309,8 +1590,8 @@ impl Engine3d {
       // screen_x = (xx+ww)*viewport_width / (2*ww) + viewport_x1
       // screen_y = (yy+ww)*viewport_height / (2*ww) + viewport_y1
 
-      temp.screen_x = if transformed[3] == 0 {
-        0
+      (temp.screen_x, temp.subpixel_x) = if transformed[3] == 0 {
+        (0, 0)
       } else {
         let mut w = transformed[3];
         let mut x_offset = transformed[0] + w;
@@ -1321,11 +1602,15 @@ impl Engine3d {
         }
 
         let denominator = 2 * w;
-        (x_offset * self.viewport.width() / denominator + self.viewport.x1 as i32) as u32
+
+        (
+          (x_offset * self.viewport.width() / denominator + self.viewport.x1 as i32) as u32,
+          (x_offset as i64 * self.viewport.width() as i64 * 16 / denominator as i64 + self.viewport.x1 as i64 * 16) as u32
+        )
       };
 
-      temp.screen_y = if transformed[3] == 0 {
-        0
+      (temp.screen_y, temp.subpixel_y) = if transformed[3] == 0 {
+        (0, 0)
       } else {
         let mut w = transformed[3];
         let mut y_offset = -transformed[1] + w;
@@ -1336,7 +1621,11 @@ impl Engine3d {
         }
 
         let denominator = 2 * w;
-        (y_offset * self.viewport.height() / denominator + self.viewport.y1 as i32) as u32
+
+        (
+          (y_offset * self.viewport.height() / denominator + self.viewport.y1 as i32) as u32,
+          (y_offset as i64 * self.viewport.height() as i64 * 16 / denominator as i64 + self.viewport.y1 as i64 * 16) as u32
+        )
       };
 
       let w = if transformed[3] != 0 {
@@ -1345,12 +1634,8 @@ impl Engine3d {
         1
       };
 
-      // TODO: get depth buffering with z working properly
-      temp.z_depth = if self.depth_buffering_with_w && false {
-        w as u32 // & !((((1_u64 << (32 - w_leading_zeros)) - 1) as u32) >> 16)
-      } else {
-        ((((transformed[2] as i64 * 0x4000 / w as i64) + 0x3fff) * 0x200)).clamp(0, 0xff_ffff) as u32
-      };
+      // the W-buffer uses transformed[3] directly, which mode is used is only known at render time
+      temp.z_depth = ((((transformed[2] as i64 * 0x4000 / w as i64) + 0x3fff) * 0x200)).clamp(0, 0xff_ffff) as u32;
 
 
       temp.normalized_w = if size < 16 {
@@ -1359,11 +1644,11 @@ impl Engine3d {
         transformed[3] >> (size - 16)
       } as i16;
 
-      if vertex.screen_y < top {
-        top = vertex.screen_y;
+      if temp.screen_y < top {
+        top = temp.screen_y;
       }
-      if vertex.screen_y > bottom {
-        bottom = vertex.screen_y;
+      if temp.screen_y > bottom {
+        bottom = temp.screen_y;
       }
 
 
@@ -1438,6 +1723,8 @@ impl Engine3d {
       transformed: [x, y, z, w as i32],
       screen_x: 0,
       screen_y: 0,
+      subpixel_x: 0,
+      subpixel_y: 0,
       z_depth: 0,
       x: 0, // these don't matter after this point, todo: maybe merge transformed and these together
       y: 0, // see above
@@ -1575,6 +1862,11 @@ impl Engine3d {
   }
 
   pub fn push_command(&mut self, entry: GeometryCommandEntry, interrupt_request: &mut InterruptRequestRegister) {
+    // stays set until the test has been executed, which might be a while if the engine is waiting on a buffer swap
+    if entry.command.is_test() {
+      self.gxstat.test_busy = true;
+    }
+
     self.fifo.push_back(entry);
 
     self.execute_commands(interrupt_request);
@@ -1606,4 +1898,29 @@ impl Engine3d {
     self.process_commands(value, interrupt_request);
 
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{test_scene::{self, TestVertex}, Engine3d};
+
+  #[test]
+  fn strips_only_count_their_shared_vertices_once() {
+    let mut engine = Engine3d::new();
+
+    let commands = test_scene::frame_commands(0x1f << 16 | 0xc0, 2, &[
+      TestVertex::new(4, 2, 0x1f),
+      TestVertex::new(4, 10, 0x1f),
+      TestVertex::new(12, 2, 0x1f),
+      TestVertex::new(12, 10, 0x1f),
+      TestVertex::new(20, 2, 0x1f)
+    ]);
+
+    // everything but SwapBuffers, which would render the frame and reset the counts
+    for entry in &commands[..commands.len() - 1] {
+      engine.execute_command(*entry);
+    }
+
+    assert_eq!(engine.read_ram_count(), 3 | 5 << 16);
+  }
+}
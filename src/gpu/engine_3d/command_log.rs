@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gpu::vram::VRam;
+
+use super::{Command, Engine3d, GeometryCommandEntry};
+
+/*
+  Records every geometry command executed over the course of one frame, starting at a
+  frame boundary (right after the previous frame has been rendered) and ending at the
+  next SwapBuffers. Along with the engine state at the start of the frame and a snapshot
+  of VRAM at render time, this is enough to reproduce the frame without the CPUs.
+*/
+pub struct CommandLog {
+  pub engine: Vec<u8>,
+  pub commands: Vec<GeometryCommandEntry>,
+  pub finished: bool
+}
+
+impl CommandLog {
+  pub fn new(engine: Vec<u8>) -> Self {
+    Self {
+      engine,
+      commands: Vec::new(),
+      finished: false
+    }
+  }
+
+  pub fn push(&mut self, entry: GeometryCommandEntry) {
+    if !self.finished {
+      self.commands.push(entry);
+
+      if entry.command == Command::SwapBuffers {
+        self.finished = true;
+      }
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GxDump {
+  pub engine: Box<Engine3d>,
+  pub commands: Vec<GeometryCommandEntry>,
+  pub vram: VRam
+}
+
+impl GxDump {
+  pub fn to_bytes(&self) -> Vec<u8> {
+    bincode::serialize(self).unwrap()
+  }
+
+  pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+    bincode::deserialize(buf).ok()
+  }
+
+  /// Re-executes the recorded command stream on a copy of the recorded engine and
+  /// renders the frame using the VRAM snapshot. The result is in `Engine3d::frame_buffer`.
+  pub fn replay(&self) -> Engine3d {
+    let mut engine = self.engine.duplicate();
+
+    // anything still sitting in the fifo at the start of the frame was recorded when it executed
+    engine.fifo.clear();
+    engine.packed_commands = 0;
+
+    for entry in &self.commands {
+      engine.execute_command(*entry);
+    }
+
+    engine.start_rendering(&self.vram);
+
+    engine
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::gpu::{vram::VRam, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+  use super::{Command, Engine3d, GeometryCommandEntry, GxDump};
+
+  /// A viewport covering the whole screen and a single opaque triangle, with identity matrices.
+  fn triangle_commands() -> Vec<GeometryCommandEntry> {
+    use Command::*;
+
+    [
+      (Viewport, 0xbfff_0000),
+      (MtxMode, 0),
+      (MtxIdentity, 0),
+      (MtxMode, 2),
+      (MtxIdentity, 0),
+      (PolygonAttr, 0x1f << 16 | 0xc0),
+      (TexImageParam, 0),
+      (BeginVtxs, 0),
+      (Color, 0x7c00),
+      (Vtx16, 0xf800_f800),
+      (Vtx16, 0),
+      (Color, 0x03e0),
+      (Vtx16, 0xf800_0800),
+      (Vtx16, 0),
+      (Color, 0x001f),
+      (Vtx16, 0x0800_0000),
+      (Vtx16, 0),
+      (EndVtxs, 0),
+      (SwapBuffers, 0)
+    ]
+    .into_iter()
+    .map(|(command, param)| GeometryCommandEntry::from(command, param))
+    .collect()
+  }
+
+  #[test]
+  fn replayed_dump_renders_the_same_frame() {
+    let vram = VRam::new();
+
+    let mut engine = Engine3d::new();
+
+    // rendering registers are written through io, so the dump has to pick them up separately
+    engine.write_clear_depth(0x7fff);
+
+    engine.start_command_log();
+
+    // the log starts at the next frame boundary
+    engine.start_rendering(&vram);
+
+    for entry in triangle_commands() {
+      engine.execute_command(entry);
+    }
+
+    engine.start_rendering(&vram);
+
+    let dump = engine.take_gx_dump().expect("the frame should have been dumped");
+
+    assert_eq!(dump.commands.len(), triangle_commands().len());
+
+    let dump = GxDump::from_bytes(&dump.to_bytes()).expect("the dump should deserialize");
+
+    let replayed = dump.replay();
+
+    let drawn = engine.frame_buffer.iter().filter(|pixel| pixel.color.is_some()).count();
+
+    assert!(drawn > 0 && drawn < SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize);
+
+    for (expected, actual) in engine.frame_buffer.iter().zip(replayed.frame_buffer.iter()) {
+      assert_eq!(expected.color, actual.color);
+      assert_eq!(expected.depth, actual.depth);
+    }
+  }
+}
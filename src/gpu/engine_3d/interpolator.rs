@@ -0,0 +1,127 @@
+/*
+  Fixed point interpolation the way the DS's rendering engine does it. Attributes are
+  interpolated perspective correctly using the (normalized) W of both endpoints, with a
+  9 bit factor along polygon edges and an 8 bit factor along scanlines. When both W values
+  are equal and have their low bits clear, hardware falls back to linear interpolation.
+
+  Depth is a special case: Z-buffer values are always interpolated linearly in screen space,
+  W-buffer values use the same perspective correct factor as everything else.
+*/
+pub struct Interpolator {
+  x0: i32,
+  x: i32,
+  x_diff: i32,
+  x_reciprocal: i64,
+  w0_numerator: i64,
+  w0_denominator: i64,
+  w1_denominator: i64,
+  shift: u32,
+  linear: bool,
+  factor: i64
+}
+
+impl Interpolator {
+  pub fn new(x0: i32, x1: i32, w0: i32, w1: i32, along_edge: bool) -> Self {
+    let x_diff = x1 - x0;
+
+    let x_reciprocal = if x_diff != 0 {
+      (1 << 22) / x_diff as i64
+    } else {
+      0
+    };
+
+    let linear = w0 == w1 && w0 & 0x7e == 0 && w1 & 0x7e == 0;
+
+    let (w0_numerator, w0_denominator, w1_denominator, shift) = if along_edge {
+      if w0 & 0b1 == 1 && w1 & 0b1 == 0 {
+        (w0 - 1, w0 + 1, w1, 9)
+      } else {
+        (w0 & 0xfffe, w0 & 0xfffe, w1 & 0xfffe, 9)
+      }
+    } else {
+      (w0, w0, w1, 8)
+    };
+
+    Self {
+      x0,
+      x: 0,
+      x_diff,
+      x_reciprocal,
+      w0_numerator: w0_numerator as i64,
+      w0_denominator: w0_denominator as i64,
+      w1_denominator: w1_denominator as i64,
+      shift,
+      linear,
+      factor: 0
+    }
+  }
+
+  pub fn set_x(&mut self, x: i32) {
+    self.x = x - self.x0;
+
+    if self.x_diff == 0 {
+      return;
+    }
+
+    let x = self.x as i64;
+
+    self.factor = if self.linear {
+      (x << self.shift) / self.x_diff as i64
+    } else {
+      let numerator = (x * self.w0_numerator) << self.shift;
+      let denominator = x * self.w0_denominator + (self.x_diff as i64 - x) * self.w1_denominator;
+
+      if denominator == 0 {
+        0
+      } else {
+        numerator / denominator
+      }
+    };
+  }
+
+  pub fn interpolate(&self, y0: i32, y1: i32) -> i32 {
+    if self.x_diff == 0 || y0 == y1 {
+      return y0;
+    }
+
+    let (y0, y1) = (y0 as i64, y1 as i64);
+
+    if y0 < y1 {
+      (y0 + (((y1 - y0) * self.factor) >> self.shift)) as i32
+    } else {
+      (y1 + (((y0 - y1) * ((1 << self.shift) - self.factor)) >> self.shift)) as i32
+    }
+  }
+
+  pub fn interpolate_depth(&self, z0: u32, z1: u32, w_buffer: bool) -> u32 {
+    if self.x_diff == 0 || z0 == z1 {
+      return z0;
+    }
+
+    if w_buffer {
+      return self.interpolate(z0 as i32, z1 as i32) as u32;
+    }
+
+    let (base, mut displacement, factor) = if z0 < z1 {
+      (z0 as i64, (z1 - z0) as i64, self.x as i64)
+    } else {
+      (z1 as i64, (z0 - z1) as i64, (self.x_diff - self.x) as i64)
+    };
+
+    if self.shift == 9 {
+      // along edges the difference is reduced to 10 bits first, losing some precision
+      let mut shift = 0;
+
+      while displacement > 0x3ff {
+        displacement >>= 1;
+        shift += 1;
+      }
+
+      (base + (((displacement * factor * self.x_reciprocal) >> 22) << shift)) as u32
+    } else {
+      displacement >>= 9;
+
+      (base + ((displacement * factor * self.x_reciprocal) >> 13)) as u32
+    }
+  }
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-use super::{polygon_attributes::PolygonAttributes, texture_params::TextureParams, PrimitiveType};
+use super::{
+  polygon_attributes::PolygonAttributes,
+  texture_params::{TextureFormat, TextureParams},
+  PrimitiveType
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Polygon {
@@ -13,4 +17,19 @@ pub struct Polygon {
   pub top: u32,
   pub bottom: u32,
   pub primitive_type: PrimitiveType
-}
\ No newline at end of file
+}
+
+impl Polygon {
+  /// Whether the polygon gets sorted with the translucent polygons. Wireframe polygons (alpha 0)
+  /// count as opaque, while any polygon using a translucent texture format doesn't.
+  pub fn is_translucent(&self) -> bool {
+    let alpha = self.attributes.alpha();
+
+    let has_translucent_texture = matches!(
+      self.tex_params.texture_format,
+      TextureFormat::A3I5Translucent | TextureFormat::A5I3Translucent
+    );
+
+    (alpha != 0 && alpha != 0x1f) || has_translucent_texture
+  }
+}
@@ -0,0 +1,310 @@
+use crate::gpu::{
+  color::Color,
+  registers::display_3d_control_register::Display3dControlRegister,
+  SCREEN_HEIGHT,
+  SCREEN_WIDTH
+};
+
+use std::ops::Range;
+
+use super::Engine3d;
+
+impl Engine3d {
+  /// Effects that the hardware applies to the given rows once every polygon has been drawn,
+  /// in the same order: edge marking, fog, then anti-aliasing.
+  pub fn apply_post_processing(&mut self, rows: Range<u32>) {
+    if self.disp3dcnt.contains(Display3dControlRegister::EDGE_MARKING_ENABLE) {
+      self.apply_edge_marking(rows.clone());
+    }
+
+    let width = SCREEN_WIDTH as usize * self.resolution_scale();
+
+    let pixels = rows.start as usize * width..rows.end as usize * width;
+
+    if self.disp3dcnt.contains(Display3dControlRegister::FOG_MASTER_ENABLE) {
+      self.apply_fog(pixels.clone());
+    }
+
+    if self.disp3dcnt.contains(Display3dControlRegister::ANTI_ALIASING_ENABLE) {
+      self.apply_anti_aliasing(pixels);
+    }
+  }
+
+  fn apply_edge_marking(&mut self, rows: Range<u32>) {
+    let clear_polygon_id = self.clear_color.clear_polygon_id as u32 & 0x3f;
+
+    let scale = self.resolution_scale() as i32;
+
+    let (width, height) = (SCREEN_WIDTH as i32 * scale, SCREEN_HEIGHT as i32 * scale);
+
+    let edge_colors = self.edge_colors.map(|mut color| color.to_rgb6());
+
+    for y in rows.start as i32..rows.end as i32 {
+      for x in 0..width {
+        let index = (x + y * width) as usize;
+
+        let attributes = self.attributes_buffer[index];
+
+        if !attributes.is_edge || attributes.is_translucent {
+          continue;
+        }
+
+        let depth = self.frame_buffer[index].depth;
+
+        // pixels outside of the screen count as the clear plane
+        let is_border = |neighbor_x: i32, neighbor_y: i32| {
+          let (neighbor_id, neighbor_depth) = if neighbor_x < 0 || neighbor_x >= width || neighbor_y < 0 || neighbor_y >= height {
+            (clear_polygon_id, self.clear_depth)
+          } else {
+            let neighbor_index = (neighbor_x + neighbor_y * width) as usize;
+
+            (self.attributes_buffer[neighbor_index].polygon_id, self.frame_buffer[neighbor_index].depth)
+          };
+
+          neighbor_id != attributes.polygon_id && depth < neighbor_depth
+        };
+
+        if is_border(x - 1, y) || is_border(x + 1, y) || is_border(x, y - 1) || is_border(x, y + 1) {
+          let edge_color = edge_colors[(attributes.polygon_id >> 3) as usize];
+
+          let pixel = &mut self.frame_buffer[index];
+
+          pixel.color = Some(Color {
+            r: edge_color.r,
+            g: edge_color.g,
+            b: edge_color.b,
+            alpha: pixel.color.and_then(|color| color.alpha)
+          });
+        }
+      }
+    }
+  }
+
+  fn apply_fog(&mut self, pixels: Range<usize>) {
+    let fog_offset = self.fog_offset as u32 * 0x200;
+    let fog_shift = self.disp3dcnt.fog_depth_shift();
+
+    let mut fog_color = self.fog_color;
+
+    fog_color.to_rgb6();
+
+    let fog_alpha = fog_color.alpha.unwrap_or(0) as u32;
+
+    for (pixel, attributes) in self.frame_buffer[pixels.clone()].iter_mut().zip(self.attributes_buffer[pixels].iter()) {
+      if !attributes.fog_enabled {
+        continue;
+      }
+
+      let Some(mut color) = pixel.color else {
+        continue;
+      };
+
+      let density = Self::get_fog_density(&self.fog_table, fog_offset, fog_shift, pixel.depth);
+
+      let blend = |fog: u32, current: u32| ((fog * density + current * (128 - density)) / 128) as u8;
+
+      if !self.disp3dcnt.contains(Display3dControlRegister::FOG_ALPHA_ONLY) {
+        color.r = blend(fog_color.r as u32, color.r as u32);
+        color.g = blend(fog_color.g as u32, color.g as u32);
+        color.b = blend(fog_color.b as u32, color.b as u32);
+      }
+
+      color.alpha = Some(blend(fog_alpha, color.alpha.unwrap_or(0x1f) as u32));
+
+      pixel.color = Some(color);
+    }
+  }
+
+  /// Blends the partially covered edge pixels of opaque polygons with whatever was behind them.
+  /// Translucent pixels are left alone, as are edges over a transparent background, where
+  /// only the alpha gets blended.
+  fn apply_anti_aliasing(&mut self, pixels: Range<usize>) {
+    for (pixel, attributes) in self.frame_buffer[pixels.clone()].iter_mut().zip(self.attributes_buffer[pixels].iter()) {
+      if !attributes.is_edge || attributes.is_translucent || attributes.coverage >= 0x1f {
+        continue;
+      }
+
+      let Some(mut color) = pixel.color else {
+        continue;
+      };
+
+      let below = pixel.below.unwrap_or(Color::new());
+      let below_alpha = below.alpha.unwrap_or(0) as u32;
+
+      let coverage = attributes.coverage as u32 + 1;
+
+      let blend = |top: u8, bottom: u8| ((top as u32 * coverage + bottom as u32 * (32 - coverage)) / 32) as u8;
+
+      if below_alpha != 0 {
+        color.r = blend(color.r, below.r);
+        color.g = blend(color.g, below.g);
+        color.b = blend(color.b, below.b);
+      }
+
+      color.alpha = Some(blend(color.alpha.unwrap_or(0x1f), below_alpha as u8));
+
+      pixel.color = Some(color);
+    }
+  }
+
+  /// Interpolates between the two fog table entries surrounding the depth. Returns 0-128.
+  fn get_fog_density(fog_table: &[u8], fog_offset: u32, fog_shift: u32, depth: u32) -> u32 {
+    let (index, fraction) = if depth < fog_offset {
+      (0, 0)
+    } else {
+      let z = (((depth - fog_offset) >> 2) as u64) << fog_shift;
+
+      let index = z >> 17;
+
+      if index >= 32 {
+        (32, 0)
+      } else {
+        (index as u32, z as u32 & 0x1_ffff)
+      }
+    };
+
+    // the table is effectively padded with its first entry at the front and its last at the end
+    let entry = |i: u32| {
+      let density = if i == 0 {
+        fog_table[0]
+      } else {
+        fog_table[(i as usize - 1).min(31)]
+      } as u32;
+
+      if density == 127 {
+        128
+      } else {
+        density
+      }
+    };
+
+    (entry(index) * (0x2_0000 - fraction) + entry(index + 1) * fraction) >> 17
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::gpu::{
+    color::Color,
+    engine_3d::{test_scene::{self, TestVertex}, Pixel3d},
+    SCREEN_WIDTH
+  };
+
+  use super::{Display3dControlRegister, Engine3d};
+
+  fn edge_pixel(engine: &mut Engine3d, index: usize, coverage: u8, below: Option<Color>) {
+    engine.frame_buffer[index] = Pixel3d {
+      color: Some(Color { r: 0x3f, g: 0x3f, b: 0x3f, alpha: Some(0x1f) }),
+      depth: 0,
+      alpha: 0,
+      below
+    };
+
+    engine.attributes_buffer[index].is_edge = true;
+    engine.attributes_buffer[index].coverage = coverage;
+  }
+
+  #[test]
+  fn edges_blend_with_what_is_below_by_coverage() {
+    let mut engine = Engine3d::new();
+
+    let black = Some(Color { r: 0, g: 0, b: 0, alpha: Some(0x1f) });
+
+    for coverage in 0..0x20 {
+      edge_pixel(&mut engine, coverage as usize, coverage, black);
+    }
+
+    // only the alpha is blended over a transparent background
+    edge_pixel(&mut engine, 32, 15, None);
+
+    // translucent pixels are never anti-aliased
+    edge_pixel(&mut engine, 33, 15, black);
+    engine.attributes_buffer[33].is_translucent = true;
+
+    engine.apply_anti_aliasing(0..34);
+
+    for coverage in 0..0x20 {
+      let color = engine.frame_buffer[coverage].color.unwrap();
+
+      let expected = (0x3f * (coverage as u32 + 1) / 32) as u8;
+
+      assert_eq!((color.r, color.g, color.b), (expected, expected, expected));
+    }
+
+    assert_eq!(engine.frame_buffer[32].color, Some(Color { r: 0x3f, g: 0x3f, b: 0x3f, alpha: Some(0xf) }));
+    assert_eq!(engine.frame_buffer[33].color, Some(Color { r: 0x3f, g: 0x3f, b: 0x3f, alpha: Some(0x1f) }));
+  }
+
+  #[test]
+  fn edge_marking_leaves_the_edge_colors_alone() {
+    let mut engine = Engine3d::new();
+
+    engine.write_edge_color(0x400_0332, 0x10 << 5 | 0x1f);
+    engine.clear_depth = 0x7fff;
+
+    let edge_colors = engine.edge_colors;
+
+    // a row along the top of the screen, which borders the clear plane, all in polygon id group 1
+    for index in 0..16 {
+      engine.frame_buffer[index] = Pixel3d {
+        color: Some(Color { r: 0, g: 0, b: 0, alpha: Some(0x1f) }),
+        depth: 0,
+        alpha: 0,
+        below: None
+      };
+
+      engine.attributes_buffer[index].is_edge = true;
+      engine.attributes_buffer[index].polygon_id = 8 + (index as u32 & 0x7);
+    }
+
+    engine.apply_edge_marking(0..1);
+
+    for index in 0..16 {
+      assert_eq!(engine.frame_buffer[index].color, Some(Color { r: 0x3f, g: 0x21, b: 0, alpha: Some(0x1f) }));
+    }
+
+    assert_eq!(engine.edge_colors, edge_colors);
+  }
+
+  /// Renders a black triangle over a white background, with a shallow edge along its bottom right.
+  fn render_shallow_edge(anti_aliasing: bool) -> Engine3d {
+    let mut engine = Engine3d::new();
+
+    engine.write_clear_color(0x1f << 16 | 0x7fff);
+
+    if anti_aliasing {
+      engine.write_disp3dcnt(Display3dControlRegister::ANTI_ALIASING_ENABLE.bits() as u16);
+    }
+
+    let commands = test_scene::frame_commands(0x1f << 16 | 0xc0, 0, &[
+      TestVertex::new(10, 10, 0),
+      TestVertex::new(200, 10, 0),
+      TestVertex::new(10, 40, 0)
+    ]);
+
+    test_scene::render(&mut engine, &commands);
+
+    engine
+  }
+
+  /// The shades in between black and white on each of the rows the shallow edge goes through.
+  fn edge_runs(engine: &Engine3d) -> Vec<Vec<u8>> {
+    (11..40).map(|y| {
+      (0..SCREEN_WIDTH as usize)
+        .map(|x| engine.frame_buffer[x + y * SCREEN_WIDTH as usize].color.unwrap().r)
+        .filter(|r| *r != 0 && *r != 0x3f)
+        .collect()
+    }).collect()
+  }
+
+  #[test]
+  fn shallow_edges_fade_into_the_background() {
+    for run in edge_runs(&render_shallow_edge(true)) {
+      // the edge moves about 6 pixels per row, fading out the further it gets from the polygon
+      assert!(run.len() >= 5, "{run:?}");
+      assert!(run.windows(2).all(|pair| pair[0] < pair[1]), "{run:?}");
+    }
+
+    assert!(edge_runs(&render_shallow_edge(false)).iter().all(|run| run.is_empty()));
+  }
+}
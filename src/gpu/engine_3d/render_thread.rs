@@ -0,0 +1,121 @@
+use std::{
+  mem,
+  sync::mpsc::{self, Receiver, Sender},
+  thread
+};
+
+use crate::gpu::vram::VRam;
+
+use super::Engine3d;
+
+/*
+  Renders frames on a worker thread instead of the emulation thread. When rendering would
+  normally start, the polygon list and rendering registers are moved into a render-only
+  engine and sent to the worker along with a copy of the banks mapped to texture and
+  texture palette memory. The finished frame buffer is swapped in before the first scanline
+  of the next frame gets composited. Both the engine and the VRAM copy come back with the
+  result so they can be reused for the next frame.
+
+  The worker only ever sees that snapshot, so VRAM being changed while the frame is being
+  rendered doesn't affect the result, and the output is identical to rendering on the
+  emulation thread.
+*/
+pub struct RenderThread {
+  jobs: Sender<(Box<Engine3d>, Box<VRam>)>,
+  results: Receiver<(Box<Engine3d>, Box<VRam>)>,
+  spare: Option<(Box<Engine3d>, Box<VRam>)>,
+  pending: bool
+}
+
+impl RenderThread {
+  pub fn new() -> Self {
+    let (jobs, job_receiver) = mpsc::channel::<(Box<Engine3d>, Box<VRam>)>();
+    let (result_sender, results) = mpsc::channel();
+
+    // the worker exits once the sending half of the job channel is dropped along with this struct
+    thread::spawn(move || {
+      for (mut engine, vram) in job_receiver {
+        engine.render_frame(&vram);
+
+        if result_sender.send((engine, vram)).is_err() {
+          break;
+        }
+      }
+    });
+
+    Self {
+      jobs,
+      results,
+      spare: None,
+      pending: false
+    }
+  }
+}
+
+impl Default for RenderThread {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Engine3d {
+  pub fn set_threaded_rendering(&mut self, enabled: bool) {
+    if enabled && self.render_thread.is_none() {
+      self.render_thread = Some(RenderThread::new());
+    } else if !enabled && self.render_thread.is_some() {
+      self.finish_rendering();
+
+      self.render_thread = None;
+    }
+  }
+
+  /// Hands the frame off to the worker thread. Returns false if threaded rendering is disabled.
+  pub fn start_render_job(&mut self, vram: &VRam) -> bool {
+    let Some(mut render_thread) = self.render_thread.take() else {
+      return false;
+    };
+
+    let (mut engine, mut snapshot) = render_thread
+      .spare
+      .take()
+      .unwrap_or_else(|| (Box::new(Engine3d::new()), Box::new(VRam::new())));
+
+    snapshot.copy_texture_banks(vram);
+
+    engine.copy_rendering_registers(self);
+    engine.set_resolution_scale(self.resolution_scale());
+
+    engine.depth_buffering_with_w = self.depth_buffering_with_w;
+    engine.transluscent_polygon_sort = self.transluscent_polygon_sort;
+    engine.debug_on = self.debug_on;
+    engine.texture_replacements = self.texture_replacements.clone();
+
+    mem::swap(&mut engine.polygon_buffer, &mut self.polygon_buffer);
+    mem::swap(&mut engine.vertices_buffer, &mut self.vertices_buffer);
+
+    render_thread.pending = render_thread.jobs.send((engine, snapshot)).is_ok();
+
+    self.render_thread = Some(render_thread);
+
+    true
+  }
+
+  /// Waits for the frame being rendered on the worker thread (if any) and swaps it into the frame buffer.
+  pub fn finish_rendering(&mut self) {
+    let Some(render_thread) = &mut self.render_thread else {
+      return;
+    };
+
+    if !render_thread.pending {
+      return;
+    }
+
+    render_thread.pending = false;
+
+    if let Ok((mut engine, snapshot)) = render_thread.results.recv() {
+      mem::swap(&mut self.frame_buffer, &mut engine.frame_buffer);
+
+      render_thread.spare = Some((engine, snapshot));
+    }
+  }
+}
@@ -1,4 +1,4 @@
-use std::{cmp, collections::HashSet};
+use std::{cmp, collections::HashMap, mem, ops::Range};
 
 use crate::gpu::{
   color::Color,
@@ -9,220 +9,175 @@ use crate::gpu::{
 };
 
 use super::{
+  interpolator::Interpolator,
   polygon::Polygon,
   polygon_attributes::{
     PolygonAttributes,
     PolygonMode
   },
   rendering_attributes::RenderingAttributes,
-  texture_params::TextureFormat,
+  slope::Slope,
+  texture_cache::{ReplacementTexture, TextureKey},
+  texture_params::{TextureFormat, TextureParams},
   vertex::Vertex,
   Engine3d,
   Pixel3d
 };
 
-pub struct Deltas {
-  pub dw: f32,
-  pub dz: f32
-}
+impl Engine3d {
+  pub fn start_rendering(&mut self, vram: &VRam) {
+    if self.polygons_ready {
+      if self.capture_textures {
+        self.frame_textures = Self::get_frame_textures(&self.polygon_buffer, vram);
+      }
 
-impl Deltas {
-  pub fn new(dz: f32, dw: f32) -> Self {
-    Self {
-      dw,
-      dz
+      if self.scanline_rendering {
+        self.start_scanline_frame(vram);
+      } else if !self.start_render_job(vram) {
+        self.render_frame(vram);
+      }
+
+      self.vertices_buffer.clear();
+      self.vertex_ram_count = 0;
+      self.polygons_ready = false;
+      self.gxstat.geometry_engine_busy = false;
     }
+
+    self.update_command_log(vram);
   }
 
-  pub fn get_deltas(start: Vertex, end: Vertex) -> Self {
-    let num_steps = end.screen_y as f32 - start.screen_y as f32;
+  /// Draws every polygon in the polygon buffer to the frame buffer, then applies post processing.
+  pub fn render_frame(&mut self, vram: &VRam) {
+    let (polygons, vertices) = self.take_sorted_polygons();
 
-    let dw = (end.normalized_w as f32 - start.normalized_w as f32) / num_steps as f32;
-    let dz = (end.z_depth as f32 - start.z_depth as f32) / num_steps as f32;
+    let replacement_hashes = self.get_replacement_hashes(&polygons, vram);
 
-    Self::new(dz, dw)
-  }
-}
-
-#[derive(Debug)]
-pub struct Slope {
-  current: usize,
-  start: f32,
-  num_steps: f32,
-  diff: f32
-}
+    let rows = 0..SCREEN_HEIGHT as u32 * self.resolution_scale() as u32;
 
-impl Slope {
-  pub fn new(start: f32, diff: f32, num_steps: f32) -> Self {
-    Self {
-      start,
-      current: 0,
-      diff,
-      num_steps
-    }
+    self.clear_rows(rows.clone(), vram);
+    self.render_polygons(&polygons, &vertices, &replacement_hashes, rows.clone(), vram);
+    self.apply_post_processing(rows);
   }
 
-  pub fn next(&mut self) -> f32 {
-    let current = self.current as f32;
-    let factor = current  / ((self.num_steps - current) + (current));
-    self.current += 1;
-    self.start + factor * self.diff
-  }
+  /// Takes the polygons out of the polygon buffer in the order they get drawn, along with their
+  /// vertices moved to wherever they are in the (possibly upscaled) frame buffer.
+  pub fn take_sorted_polygons(&mut self) -> (Vec<Polygon>, Vec<Vertex>) {
+    let scale = self.resolution_scale() as u32;
 
-  pub fn get_texture_slope(start: Vertex, end: Vertex, is_u: bool) -> Self {
-    let (start_fp, end_fp) = if is_u {
-      (start.texcoord.u as f32, end.texcoord.u as f32)
+    let vertices = if scale > 1 {
+      Self::get_scaled_vertices(&self.vertices_buffer, scale)
     } else {
-      (start.texcoord.v as f32, end.texcoord.v as f32)
+      mem::take(&mut self.vertices_buffer)
     };
 
-    Slope::new(
-      start_fp,
-      end_fp - start_fp,
-      (end.screen_y - start.screen_y) as f32
-    )
-  }
-}
-
-#[derive(Debug)]
-pub struct RgbSlopes {
-  r_slope: Slope,
-  g_slope: Slope,
-  b_slope: Slope
-}
+    // opaque polygons always get drawn before translucent ones, and both are sorted by their
+    // bottom and then top Y coordinates, unless the game chose to sort translucent polygons itself
+    let (mut opaque, mut translucent): (Vec<Polygon>, Vec<Polygon>) =
+      self.polygon_buffer.drain(..).partition(|polygon| !polygon.is_translucent());
 
-impl RgbSlopes {
-  pub fn get_slopes(start: Vertex, end: Vertex) -> RgbSlopes {
-    let r_slope = Self::new_slope(start, end, start.color.r as f32, end.color.r as f32);
-    let g_slope = Self::new_slope(start, end, start.color.g as f32, end.color.g as f32);
-    let b_slope = Self::new_slope(start, end, start.color.b as f32, end.color.b as f32);
+    opaque.sort_by_key(|polygon| (polygon.bottom, polygon.top));
 
-    Self {
-      r_slope,
-      g_slope,
-      b_slope
+    if !self.transluscent_polygon_sort {
+      translucent.sort_by_key(|polygon| (polygon.bottom, polygon.top));
     }
-  }
-  pub fn new(start: Color, end: Color, num_steps: f32) -> Self {
-    let r_slope = Slope::new(
-      start.r as f32,
-      (end.r as i32 - start.r as i32) as f32,
-      num_steps
-    );
 
-    let g_slope = Slope::new(
-      start.g as f32,
-      (end.g as i32 - start.g as i32) as f32,
-      num_steps
-    );
+    opaque.append(&mut translucent);
 
-    let b_slope = Slope::new(
-      start.b as f32,
-      (end.b as i32 - start.b as i32) as f32,
-      num_steps
-    );
+    (opaque, vertices)
+  }
+
+  /// Clears the given rows of the frame buffer to the rear plane, either using the clear color
+  /// registers or the rear plane bitmap.
+  pub fn clear_rows(&mut self, rows: Range<u32>, vram: &VRam) {
+    self.clear_attributes_buffer(rows.clone());
 
-    Self {
-      r_slope,
-      g_slope,
-      b_slope
+    if self.disp3dcnt.contains(Display3dControlRegister::REAR_PLANE_MODE) {
+      self.clear_rear_plane_bitmap(rows, vram);
+      return;
     }
-  }
 
-  pub fn new_slope(start: Vertex, end: Vertex, start_fp: f32, end_fp: f32) -> Slope {
-    Slope::new(
-      start_fp,
-      end_fp - start_fp,
-      (end.screen_y - start.screen_y) as f32
-    )
-  }
+    let width = SCREEN_WIDTH as usize * self.resolution_scale();
 
-  pub fn next_color(&mut self) -> Color {
-    let r = self.r_slope.next() as u8;
-    let g = self.g_slope.next() as u8;
-    let b = self.b_slope.next() as u8;
+    let pixels = &mut self.frame_buffer[rows.start as usize * width..rows.end as usize * width];
 
-    Color {
-      r,
-      g,
-      b,
-      alpha: None
+    if self.clear_color.alpha != 0 {
+      for pixel in pixels {
+        pixel.color = Some(Color {
+          r: self.clear_color.r,
+          g: self.clear_color.g,
+          b: self.clear_color.b,
+          alpha: Some(self.clear_color.alpha)
+        }.to_rgb6());
+        pixel.depth = self.clear_depth as u32;
+      }
+    } else {
+      for pixel in pixels {
+        *pixel = Pixel3d::new();
+        pixel.depth = self.clear_depth as u32;
+      }
     }
   }
-}
-
 
-impl Engine3d {
-  pub fn start_rendering(&mut self, vram: &VRam) {
-    if self.polygons_ready {
-      self.clear_attributes_buffer();
-      if self.clear_color.alpha != 0 {
-        for pixel in self.frame_buffer.iter_mut() {
-          pixel.color = Some(Color {
-            r: self.clear_color.r,
-            g: self.clear_color.g,
-            b: self.clear_color.b,
-            alpha: Some(self.clear_color.alpha)
-          });
-          pixel.depth = self.clear_depth as u32;
-        }
-      } else {
-        self.clear_frame_buffer();
-      }
+  /// Draws the parts of the polygons that fall inside of the given rows of the frame buffer.
+  pub fn render_polygons(
+    &mut self,
+    polygons: &[Polygon],
+    vertices: &[Vertex],
+    replacement_hashes: &HashMap<TextureKey, u64>,
+    rows: Range<u32>,
+    vram: &VRam
+  ) {
+    let scale = self.resolution_scale() as u32;
 
-      let mut render = |polygon, vertices| Self::render_polygon(
-        &polygon,
-        vertices,
-        vram,
-        &mut self.frame_buffer,
-        &self.toon_table,
-        &self.disp3dcnt,
-        &mut self.fog_color,
-        self.fog_offset,
-        &self.fog_table,
-        &mut self.attributes_buffer,
-        self.debug_on,
-        &mut self.found
-      );
+    let width = SCREEN_WIDTH as u32 * scale;
 
+    let mut last_was_shadow_mask = false;
 
-      if self.disp3dcnt.contains(Display3dControlRegister::ALPHA_BLENDING_ENABLE) {
-        let (opaque, translucent): (Vec<Polygon>, Vec<Polygon>) =
-          self.polygon_buffer.drain(..).partition(|polygon| polygon.attributes.alpha() == 0x1f);
+    for polygon in polygons {
+      let is_shadow_mask = polygon.attributes.polygon_mode() == PolygonMode::Shadow && polygon.attributes.polygon_id() == 0;
 
-        for polygon in opaque {
-          let vertices = &self.vertices_buffer[polygon.start..polygon.end];
-          render(polygon, vertices);
-        }
+      // each group of shadow masks starts with a fresh stencil buffer
+      if is_shadow_mask && !last_was_shadow_mask {
+        let attributes = &mut self.attributes_buffer[(rows.start * width) as usize..(rows.end * width) as usize];
 
-        for polygon in translucent {
-          let vertices = &self.vertices_buffer[polygon.start..polygon.end];
-          render(polygon, vertices);
+        for attributes in attributes.iter_mut() {
+          attributes.stencil = false;
         }
+      }
 
+      last_was_shadow_mask = is_shadow_mask;
 
-      } else {
-        for polygon in self.polygon_buffer.drain(..) {
-          let vertices = &self.vertices_buffer[polygon.start..polygon.end];
-          render(polygon, vertices);
-        }
+      // every scaled vertex stays inside of the block of rows its native line covers
+      if polygon.top * scale >= rows.end || (polygon.bottom + 1) * scale <= rows.start {
+        continue;
       }
 
-
-      self.vertices_buffer.clear();
-      self.polygons_ready = false;
-      self.gxstat.geometry_engine_busy = false;
+      Self::render_polygon(
+        polygon,
+        &vertices[polygon.start..polygon.end],
+        vram,
+        replacement_hashes
+          .get(&TextureKey::new(polygon))
+          .and_then(|hash| self.texture_replacements.get(hash)),
+        &mut self.frame_buffer,
+        &self.toon_table,
+        &self.disp3dcnt,
+        &mut self.attributes_buffer,
+        width,
+        rows.clone(),
+        self.depth_buffering_with_w
+      );
     }
   }
 
-  fn get_palette_color(polygon: &Polygon, palette_base: u32, palette_index: u32, vram: &VRam, alpha: Option<u8>) -> Option<Color> {
+  fn get_palette_color(tex_params: &TextureParams, palette_base: u32, palette_index: u32, vram: &VRam, alpha: Option<u8>) -> Option<Color> {
     let address = palette_base + 2 * palette_index;
 
     let color_raw = vram.read_texture_palette::<u16>(address);
 
     let mut color = Color::from(color_raw).to_rgb6();
 
-    if palette_index == 0 && polygon.tex_params.color0_transparent {
+    if palette_index == 0 && tex_params.color0_transparent {
       color.alpha = Some(0);
     } else {
       color.alpha = alpha;
@@ -231,39 +186,24 @@ impl Engine3d {
     Some(color)
   }
 
-  fn calculate_slope(start: Vertex, end: Vertex) -> f32 {
-    if start.screen_y != end.screen_y {
-      let slope = (end.screen_x as f32 - start.screen_x as f32) / (end.screen_y as f32 - start.screen_y as f32);
-
-      slope
-    } else {
-      0.0
-    }
-  }
-
   fn render_polygon(
     polygon: &Polygon,
     vertices: &[Vertex],
     vram: &VRam,
+    replacement: Option<&ReplacementTexture>,
     frame_buffer: &mut [Pixel3d],
     toon_table: &[Color],
     disp3dcnt: &Display3dControlRegister,
-    fog_color: &mut Color,
-    fog_offset: u16,
-    fog_table: &[u8],
     attributes_buffer: &mut [RenderingAttributes],
-    debug_on: bool,
-    found: &mut HashSet<String>
+    width: u32,
+    rows: Range<u32>,
+    w_buffer: bool
   ) {
-    if polygon.attributes.polygon_mode() == PolygonMode::Shadow {
-      // TODO: implement shadow mode
-      return;
-    }
+    let is_shadow = polygon.attributes.polygon_mode() == PolygonMode::Shadow;
+    let polygon_id = polygon.attributes.polygon_id();
 
     let mut min_y = vertices[0].screen_y;
     let mut max_y = vertices[0].screen_y;
-    let mut min_x = vertices[0].screen_x;
-    let mut max_x = vertices[0].screen_x;
 
     let mut start_index = 0;
     let mut end_index = 0;
@@ -284,17 +224,8 @@ impl Engine3d {
       } else if current.screen_y == vertices[end_index].screen_y && current.screen_x >= vertices[end_index].screen_x {
         end_index = i;
       }
-
-      if current.screen_x < min_x {
-        min_x = current.screen_x;
-      }
-      if current.screen_x > max_x {
-        max_x = current.screen_x;
-      }
     }
 
-    max_y = max_y.min(SCREEN_HEIGHT as u32 - 1);
-
     let mut left_start_index = start_index;
     let mut right_start_index = start_index;
 
@@ -331,32 +262,32 @@ impl Engine3d {
     left_start_index = left_end_index;
     right_start_index = right_end_index;
 
-    let mut left_slope = Self::calculate_slope(left_start, left_end);
-    let mut right_slope = Self::calculate_slope(right_start, right_end);
-
-    let mut left_vertical_u = Slope::get_texture_slope(left_start, left_end, true);
-    let mut right_vertical_u = Slope::get_texture_slope(right_start, right_end, true);
+    // polygons that are only one scanline tall are drawn from their leftmost to their rightmost vertex
+    let is_flat = min_y == max_y;
+    let bottom = if is_flat { min_y + 1 } else { max_y };
 
-    let mut left_vertical_v = Slope::get_texture_slope(left_start, left_end, false);
-    let mut right_vertical_v = Slope::get_texture_slope(right_start, right_end, false);
+    // when only some of the rows are being drawn, the edges start partway down
+    let first_y = min_y.max(rows.start);
 
-    let mut left_vertical_rgb = RgbSlopes::get_slopes(left_start, left_end);
-    let mut right_vertical_rgb = RgbSlopes::get_slopes(right_start, right_end);
+    let (mut left_slope, mut right_slope) = if is_flat {
+      let leftmost = vertices.iter().min_by_key(|vertex| vertex.screen_x).unwrap();
+      let rightmost = vertices.iter().max_by_key(|vertex| vertex.screen_x).unwrap();
 
-    let mut left_vertical_delta = Deltas::get_deltas(left_start, left_end);
-    let mut right_vertical_delta = Deltas::get_deltas(right_start, right_end);
-
-    let mut y = min_y;
-    let mut x = min_x;
+      (Slope::new_flat(*leftmost, min_y, false), Slope::new_flat(*rightmost, min_y, true))
+    } else {
+      (Slope::new(left_start, left_end, first_y, false), Slope::new(right_start, right_end, first_y, true))
+    };
 
-    let mut z_start = left_start.z_depth as f32;
-    let mut z_end = right_start.z_depth as f32;
+    let is_wireframe = polygon.attributes.alpha() == 0;
 
-    let mut boundary1 = left_start.screen_x as f32;
-    let mut boundary2 = right_start.screen_x as f32;
+    // edges always get drawn when something is going to be done with them afterwards
+    let fill_all_edges = is_wireframe ||
+      polygon.attributes.alpha() != 0x1f ||
+      disp3dcnt.contains(Display3dControlRegister::ANTI_ALIASING_ENABLE) ||
+      disp3dcnt.contains(Display3dControlRegister::EDGE_MARKING_ENABLE);
 
-    while y < max_y {
-      while y >= left_end.screen_y {
+    for y in first_y..bottom.min(rows.end) {
+      while !is_flat && y >= left_end.screen_y {
         // need to calculate a new left slope
         let left_end_index = next_left(left_start_index);
 
@@ -366,20 +297,10 @@ impl Engine3d {
         left_start_index = left_end_index;
 
         if y < left_end.screen_y {
-          left_slope = Self::calculate_slope(left_start, left_end);
-
-          left_vertical_u = Slope::get_texture_slope(left_start, left_end, true);
-          left_vertical_v = Slope::get_texture_slope(left_start, left_end, false);
-
-          left_vertical_rgb = RgbSlopes::get_slopes(left_start, left_end);
-          left_vertical_delta = Deltas::get_deltas(left_start, left_end);
-
-          boundary1 = left_start.screen_x as f32;
-
-          z_start = left_start.z_depth as f32;
+          left_slope = Slope::new(left_start, left_end, y, false);
         }
       }
-      while y >= right_end.screen_y {
+      while !is_flat && y >= right_end.screen_y {
         // need to calculate a new right slope
         let right_end_index = next_right(right_start_index);
 
@@ -389,73 +310,93 @@ impl Engine3d {
         right_start_index = right_end_index;
 
         if y < right_end.screen_y {
-          right_slope = Self::calculate_slope(right_start, right_end);
-
-          right_vertical_u = Slope::get_texture_slope(right_start, right_end, true);
-          right_vertical_v = Slope::get_texture_slope(right_start, right_end, false);
+          right_slope = Slope::new(right_start, right_end, y, true);
+        }
+      }
 
-          right_vertical_rgb = RgbSlopes::get_slopes(right_start, right_end);
-          right_vertical_delta = Deltas::get_deltas(right_start, right_end);
+      let mut left = left_slope.edge_point(w_buffer);
+      let mut right = right_slope.edge_point(w_buffer);
 
-          boundary2 = right_start.screen_x as f32;
+      let (mut left_edge, mut right_edge) = (&left_slope, &right_slope);
 
-          z_end = right_start.z_depth as f32;
-        }
+      // the edges can cross over each other near the tips of very thin polygons
+      if left.x > right.x {
+        mem::swap(&mut left, &mut right);
+        mem::swap(&mut left_edge, &mut right_edge);
       }
 
-      let left_u = left_vertical_u.next();
-      let right_u = right_vertical_u.next();
+      let left_length = left_edge.length().min(right.x + 1 - left.x);
+      let right_length = right_edge.length().min(right.x + 1 - left.x);
 
-      let left_v = left_vertical_v.next();
-      let right_v = right_vertical_v.next();
+      let left_filled = fill_all_edges || left_edge.is_filled();
+      let right_filled = fill_all_edges || right_edge.is_filled();
 
-      let left_rgb = left_vertical_rgb.next_color();
-      let right_rgb = right_vertical_rgb.next_color();
+      let mut span_interpolator = Interpolator::new(left.x, right.x + 1, left.w, right.w, false);
 
-      x = boundary1 as u32;
+      let mut x = left.x.max(0) as u32;
 
-      z_start += left_vertical_delta.dz;
-      z_end += right_vertical_delta.dz;
+      let span_end = right.x.min(width as i32 - 1);
 
-      let mut u_d = Slope::new(
-        left_u,
-        right_u - left_u,
-        boundary2 - boundary1
-      );
+      while x as i32 <= span_end {
+        let in_left_edge = (x as i32) < left.x + left_length;
+        let in_right_edge = x as i32 > right.x - right_length;
 
-      let mut v_d = Slope::new(
-        left_v,
-        right_v - left_v,
-        boundary2 - boundary1
-      );
+        let is_edge = in_left_edge || in_right_edge || y == min_y || y == bottom - 1;
 
-      let mut rgb_d = RgbSlopes::new(
-        left_rgb,
-        right_rgb,
-        boundary2 - boundary1
-      );
+        if (in_left_edge && !left_filled) || (in_right_edge && !right_filled) || (is_wireframe && !is_edge) {
+          x += 1;
+          continue;
+        }
 
-      let dzdx = (z_end - z_start) / (boundary2 - boundary1);
+        span_interpolator.set_x(x as i32);
 
-      let mut z = z_start;
+        let z = span_interpolator.interpolate_depth(left.z, right.z, w_buffer);
 
-      let boundary2_u32 = boundary2 as u32;
+        let s = span_interpolator.interpolate(left.u, right.u);
+        let t = span_interpolator.interpolate(left.v, right.v);
 
-      while x < boundary2_u32 && x < SCREEN_WIDTH as u32 {
-        let curr_u = u_d.next() as i32 >> 4;
-        let curr_v = v_d.next() as i32 >> 4;
+        let curr_u = s >> 4;
+        let curr_v = t >> 4;
 
-        let mut vertex_color = rgb_d.next_color();
+        let vertex_color = Color {
+          r: (span_interpolator.interpolate(left.r, right.r) >> 3) as u8,
+          g: (span_interpolator.interpolate(left.g, right.g) >> 3) as u8,
+          b: (span_interpolator.interpolate(left.b, right.b) >> 3) as u8,
+          // wireframe edges are drawn fully opaque
+          alpha: Some(if is_wireframe { 0x1f } else { polygon.attributes.alpha() })
+        };
 
-        vertex_color.alpha = Some(polygon.attributes.alpha());
+        let index = (x + y * width) as usize;
 
         // render the pixel!
-        let pixel = &mut frame_buffer[(x + y * SCREEN_WIDTH as u32) as usize];
-        let prev_attributes = attributes_buffer[(x + y * SCREEN_WIDTH as u32) as usize];
+        let pixel = &mut frame_buffer[index];
+        let prev_attributes = attributes_buffer[index];
+
+        if is_shadow {
+          if polygon_id == 0 {
+            // shadow masks never draw anything, they only mark where the shadow volume is behind something
+            if !Self::check_polygon_depth(polygon, pixel.depth, z, prev_attributes, w_buffer) {
+              attributes_buffer[index].stencil = true;
+            }
+
+            x += 1;
+            continue;
+          } else if !prev_attributes.stencil || prev_attributes.polygon_id == polygon_id {
+            // shadows aren't cast onto the polygons with the same id (ie: the object casting the shadow)
+            x += 1;
+            continue;
+          }
+        }
 
         let mut color: Option<Color> = None;
 
-        if let Some(texel_color) = Self::get_texel_color(polygon, curr_u, curr_v, vram, debug_on, found) {
+        let texel_color = if let Some(replacement) = replacement {
+          replacement.get_texel_color(&polygon.tex_params, s, t)
+        } else {
+          Self::get_texel_color(&polygon.tex_params, polygon.palette_base, curr_u, curr_v, vram)
+        };
+
+        if let Some(texel_color) = texel_color {
           color = if texel_color.alpha.is_some() && texel_color.alpha.unwrap() == 0 {
             None
           } else {
@@ -482,7 +423,7 @@ impl Engine3d {
                 Self::modulation_blend(texel_color, vertex_color)
               }
               PolygonMode::Shadow => {
-                todo!("shadow mode not implemented");
+                Self::modulation_blend(texel_color, vertex_color)
               }
               PolygonMode::Toon => {
                 if disp3dcnt.contains(Display3dControlRegister::POLYGON_ATTR_SHADING) {
@@ -517,107 +458,72 @@ impl Engine3d {
           color = Some(vertex_color);
         }
 
-        if let Some(mut color) = color {
+        if let Some(color) = color {
           let fb_color = pixel.color.unwrap_or(Color::new());
           let fb_alpha = fb_color.alpha.unwrap_or(0x1f);
 
           let polygon_alpha = color.alpha.unwrap();
+          let is_translucent = polygon_alpha != 0x1f;
+
+          // translucent polygons never get drawn over translucent pixels with the same polygon id
+          let same_translucent_id = is_translucent && prev_attributes.is_translucent && prev_attributes.translucent_polygon_id == polygon_id;
 
-          if Self::check_polygon_depth(
+          if polygon_alpha != 0 && !same_translucent_id && Self::check_polygon_depth(
             polygon,
             pixel.depth,
-            z as u32,
-            prev_attributes
+            z,
+            prev_attributes,
+            w_buffer
           ) {
-            if disp3dcnt.contains(Display3dControlRegister::ALPHA_BLENDING_ENABLE) && pixel.color.is_some() && fb_alpha != 0 && polygon_alpha != 0x1f {
-              let pixel_color = pixel.color.unwrap().to_rgb6();
-              let mut color = Self::blend_colors3d(pixel_color, color, 0x1f - polygon_alpha as u16, (polygon_alpha + 1) as u16);
+            if disp3dcnt.contains(Display3dControlRegister::ALPHA_BLENDING_ENABLE) && pixel.color.is_some() && fb_alpha != 0 && is_translucent {
+              let mut color = Self::blend_colors3d(pixel.color.unwrap(), color, 0x1f - polygon_alpha as u16, (polygon_alpha + 1) as u16);
 
               color.alpha = Some(cmp::max(fb_alpha, polygon_alpha));
 
-              color.to_rgb5();
-
               pixel.color = Some(color);
+            } else {
+              if !is_translucent {
+                pixel.below = pixel.color;
+              }
 
-            } else if polygon_alpha != 0 {
-              color.to_rgb5();
               pixel.color = Some(color);
-              pixel.depth = z as u32;
             }
 
-            if polygon_alpha != 0x1f && polygon.attributes.contains(PolygonAttributes::UPDATE_DEPTH_FOR_TRANSLUCENT) {
-              pixel.depth = z as u32;
+            // translucent pixels only update the depth buffer if the polygon asks for it
+            if !is_translucent || polygon.attributes.contains(PolygonAttributes::UPDATE_DEPTH_FOR_TRANSLUCENT) {
+              pixel.depth = z;
             }
 
-            attributes_buffer[(x + y * SCREEN_WIDTH as u32) as usize] = RenderingAttributes {
-              is_translucent: polygon_alpha != 0x1f,
-              front_facing: polygon.is_front,
-              fog_enabled: polygon.attributes.contains(PolygonAttributes::FOG_ENABLE)
-            };
-
-            if disp3dcnt.contains(Display3dControlRegister::FOG_MASTER_ENABLE) &&
-              polygon.attributes.contains(PolygonAttributes::FOG_ENABLE) &&
-              pixel.color.is_some()
-            {
-              let mut pixel_color = pixel.color.unwrap();
-
-              Self::apply_fog(
-                &mut pixel_color.to_rgb6(),
-                fog_color,
-                fog_offset,
-                fog_table,
-                disp3dcnt,
-                z as u32
-              );
-
-              pixel.color = Some(pixel_color.to_rgb5());
+            let attributes = &mut attributes_buffer[index];
+
+            attributes.is_translucent = is_translucent;
+            attributes.front_facing = polygon.is_front;
+            attributes.fog_enabled = polygon.attributes.contains(PolygonAttributes::FOG_ENABLE);
+
+            if is_translucent {
+              attributes.translucent_polygon_id = polygon_id;
+            } else {
+              // wireframe polygons are never anti-aliased
+              attributes.coverage = if is_wireframe {
+                0x1f
+              } else {
+                let left_coverage = if in_left_edge { left_edge.coverage(x as i32) } else { 0x1f };
+                let right_coverage = if in_right_edge { right_edge.coverage(x as i32) } else { 0x1f };
+
+                left_coverage.min(right_coverage)
+              };
+
+              attributes.polygon_id = polygon_id;
+              attributes.is_edge = is_edge;
             }
           }
         }
-        z += dzdx;
         x += 1;
       }
-      boundary1 += left_slope;
-      boundary2 += right_slope;
-      y += 1;
-    }
-  }
-
-  fn apply_fog(
-    color: &mut Color,
-    fog_color: &mut Color,
-    fog_offset: u16,
-    fog_table: &[u8],
-    disp3dcnt: &Display3dControlRegister,
-    z: u32
-  ) {
-    let fog_step = 0x400 << disp3dcnt.fog_depth_shift();
-
-    let n = if z < fog_offset as u32 {
-      (((z - fog_offset as u32) / fog_step - 1) >> 17) & 0x1f
-    } else {
-      0
-    };
 
-    let mut density = fog_table[n as usize];
-
-    if density == 127 {
-      density = 128;
-    }
-
-    fog_color.to_rgb6();
-
-    // apply fog formula
-
-    if !disp3dcnt.contains(Display3dControlRegister::FOG_ALPHA_ONLY) {
-      color.r = ((fog_color.r as u16 * density as u16 + color.r as u16 * (128 - density as u16)) / 128) as u8;
-      color.g = ((fog_color.g as u16 * density as u16 + color.g as u16 * (128 - density as u16)) / 128) as u8;
-      color.b = ((fog_color.b as u16 * density as u16 + color.b as u16 * (128 - density as u16)) / 128) as u8;
+      left_slope.step();
+      right_slope.step();
     }
-
-    color.alpha = Some(
-      ((fog_color.alpha6() as u16 * density as u16 + color.alpha6() as u16 * (128 - density as u16)) / 128) as u8
-    );
   }
 
   fn apply_polygon_shading(blended: Color, toon_color: Color) -> Option<Color> {
@@ -654,9 +560,12 @@ impl Engine3d {
     current_depth: u32,
     new_depth: u32,
     prev_attributes: RenderingAttributes,
+    w_buffer: bool
   ) -> bool {
     if polygon.attributes.contains(PolygonAttributes::DRAW_PIXELS_WITH_DEPTH) {
-      new_depth >= current_depth - 0x200 && new_depth <= current_depth + 0x200
+      let tolerance = if w_buffer { 0xff } else { 0x200 };
+
+      new_depth.abs_diff(current_depth) <= tolerance
     } else if !polygon.is_front {
       new_depth < current_depth
     } else {
@@ -703,51 +612,58 @@ impl Engine3d {
     })
   }
 
+  pub fn get_4x4_palette_info_address(base_address: u32) -> u32 {
+    128 * 0x400 + (base_address & 0x1_ffff) / 2 + if base_address >= 128 * 0x400 {
+      0x1000
+    } else {
+      0
+    }
+  }
+
+  /// Wraps a texel coordinate into the texture. Repeating textures wrap around (mirroring every
+  /// odd repetition when flipped), the rest are clamped to their first and last texel.
   fn check_if_texture_repeated(val: i32, repeat: bool, flip: bool, mask: u32, shift: u32) -> u32 {
-    let mut return_val = val as u32;
     if repeat {
-      return_val &= mask;
-      if flip && (val as u32 >> shift) % 2 == 1 {
-        return_val ^= mask;
+      let wrapped = val as u32 & mask;
+
+      // an arithmetic shift, so coordinates left of/above the texture count the repetitions correctly
+      if flip && (val >> shift) & 0b1 == 1 {
+        wrapped ^ mask
+      } else {
+        wrapped
       }
-    } else if val < 0 {
-      return_val = 0;
-    } else if val as u32 >= mask {
-      return_val = mask;
+    } else {
+      val.clamp(0, mask as i32) as u32
     }
-
-    return_val
   }
 
-  fn get_texel_color(polygon: &Polygon, curr_u: i32, curr_v: i32, vram: &VRam, debug_on: bool, found: &mut HashSet<String>) -> Option<Color> {
+  pub fn get_texel_color(tex_params: &TextureParams, palette_base: usize, curr_u: i32, curr_v: i32, vram: &VRam) -> Option<Color> {
     let u = curr_u;
 
     let u = Self::check_if_texture_repeated(
       u,
-      polygon.tex_params.repeat_s,
-      polygon.tex_params.flip_s,
-      polygon.tex_params.texture_s_size - 1,
-      polygon.tex_params.size_s_shift
+      tex_params.repeat_s,
+      tex_params.flip_s,
+      tex_params.texture_s_size - 1,
+      tex_params.size_s_shift
     );
 
     let v = curr_v;
 
     let v = Self::check_if_texture_repeated(
       v,
-      polygon.tex_params.repeat_t,
-      polygon.tex_params.flip_t,
-      polygon.tex_params.texture_t_size -1,
-      polygon.tex_params.size_t_shift
+      tex_params.repeat_t,
+      tex_params.flip_t,
+      tex_params.texture_t_size -1,
+      tex_params.size_t_shift
     );
 
-    let texel = u + v * polygon.tex_params.texture_s_size;
-    let vram_offset = polygon.tex_params.vram_offset;
+    let texel = u + v * tex_params.texture_s_size;
+    let vram_offset = tex_params.vram_offset;
 
     let address = vram_offset + texel;
 
-    let palette_base = polygon.palette_base;
-
-    match polygon.tex_params.texture_format {
+    match tex_params.texture_format {
       TextureFormat::None => None,
       TextureFormat::A3I5Translucent => {
         let byte = vram.read_texture::<u8>(address);
@@ -755,7 +671,7 @@ impl Engine3d {
         let palette_index = byte & 0x1f;
         let alpha = (byte >> 5) & 0x7;
 
-        Self::get_palette_color(polygon, palette_base as u32, palette_index as u32, vram, Some(alpha * 4 + alpha / 2))
+        Self::get_palette_color(tex_params, palette_base as u32, palette_index as u32, vram, Some(alpha * 4 + alpha / 2))
       }
       TextureFormat::A5I3Translucent => {
         let byte = vram.read_texture::<u8>(address);
@@ -764,7 +680,7 @@ impl Engine3d {
 
         let alpha = (byte >> 3) & 0x1f;
 
-        Self::get_palette_color(polygon, palette_base as u32, palette_index as u32, vram, Some(alpha))
+        Self::get_palette_color(tex_params, palette_base as u32, palette_index as u32, vram, Some(alpha))
       }
       TextureFormat::Color16 => {
         let real_address = vram_offset + texel / 2;
@@ -777,15 +693,15 @@ impl Engine3d {
           (byte >> 4) & 0xf
         };
 
-        Self::get_palette_color(polygon, palette_base as u32, palette_index as u32, vram, Some(0x1f))
+        Self::get_palette_color(tex_params, palette_base as u32, palette_index as u32, vram, Some(0x1f))
       }
       TextureFormat::Color256 => {
         let palette_index = vram.read_texture::<u8>(address);
 
-        Self::get_palette_color(polygon, palette_base as u32, palette_index as u32, vram, Some(0x1f))
+        Self::get_palette_color(tex_params, palette_base as u32, palette_index as u32, vram, Some(0x1f))
       }
       TextureFormat::Color4x4 => {
-        let blocks_per_row = polygon.tex_params.texture_s_size / 4;
+        let blocks_per_row = tex_params.texture_s_size / 4;
 
         let block_address = (u / 4) + blocks_per_row * (v / 4);
 
@@ -801,13 +717,7 @@ impl Engine3d {
           _ => unreachable!()
         };
 
-        let slot1_address = 128 * 0x400 + (base_address & 0x1_ffff) / 2 + if base_address >= 128 * 0x400 {
-          0x1000
-        } else {
-          0
-        };
-
-        let extra_palette_info = vram.read_texture::<u16>(slot1_address);
+        let extra_palette_info = vram.read_texture::<u16>(Self::get_4x4_palette_info_address(base_address));
 
         let palette_offset = palette_base as u32 + ((extra_palette_info & 0x3fff) * 4) as u32;
 
@@ -873,13 +783,13 @@ impl Engine3d {
         let address = palette_base as u32 / 2 + palette_index as u32 * 2;
         let color_raw = vram.read_texture_palette(address);
 
-        let alpha = if palette_index == 0 && polygon.tex_params.color0_transparent {
+        let alpha = if palette_index == 0 && tex_params.color0_transparent {
           Some(0)
         } else {
           Some(0x1f)
         };
 
-        let mut color = Color::from(color_raw);
+        let mut color = Color::from(color_raw).to_rgb6();
         color.alpha = alpha;
 
         Some(color)
@@ -890,7 +800,7 @@ impl Engine3d {
 
         let alpha = if color_raw & 0x8000 == 0 { Some(0) } else { Some(0x1f) };
 
-        let mut color = Color::from(color_raw);
+        let mut color = Color::from(color_raw).to_rgb6();
 
         color.alpha = alpha;
 
@@ -898,4 +808,106 @@ impl Engine3d {
       }
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::gpu::{
+    engine_3d::test_scene::{self, TestVertex},
+    SCREEN_WIDTH
+  };
+
+  use super::Engine3d;
+
+  const OPAQUE_BOTH_SIDES: u32 = 0x1f << 16 | 0xc0;
+
+  /// Renders the vertices and returns the red channel of the top left 16x12 pixels.
+  fn render(primitive_type: u32, vertices: &[TestVertex]) -> Vec<Vec<Option<u8>>> {
+    let mut engine = Engine3d::new();
+
+    test_scene::render(&mut engine, &test_scene::frame_commands(OPAQUE_BOTH_SIDES, primitive_type, vertices));
+
+    (0..12).map(|y| {
+      (0..16).map(|x| engine.frame_buffer[x + y * SCREEN_WIDTH as usize].color.map(|color| color.r)).collect()
+    }).collect()
+  }
+
+  /// Checks which pixels were drawn against a map, where each '#' is a pixel drawn in full red.
+  fn assert_coverage(pixels: &[Vec<Option<u8>>], expected: &[&str]) {
+    for (y, (row, expected_row)) in pixels.iter().zip(expected.iter()).enumerate() {
+      let row: String = row.iter().map(|pixel| match pixel {
+        Some(0x3f) => '#',
+        Some(_) => '?',
+        None => '.'
+      }).collect();
+
+      assert_eq!(row, *expected_row, "row {y}");
+    }
+  }
+
+  #[test]
+  fn triangle_leaves_out_its_right_edge_and_bottom_row() {
+    let pixels = render(0, &[
+      TestVertex::new(4, 2, 0x1f),
+      TestVertex::new(12, 2, 0x1f),
+      TestVertex::new(4, 10, 0x1f)
+    ]);
+
+    assert_coverage(&pixels, &[
+      "................",
+      "................",
+      "....#######.....",
+      "....######......",
+      "....#####.......",
+      "....####........",
+      "....###.........",
+      "....##..........",
+      "....#...........",
+      "................",
+      "................",
+      "................"
+    ]);
+  }
+
+  #[test]
+  fn triangle_strip_fills_a_rectangle_without_gaps() {
+    let pixels = render(2, &[
+      TestVertex::new(4, 2, 0x1f),
+      TestVertex::new(4, 10, 0x1f),
+      TestVertex::new(12, 2, 0x1f),
+      TestVertex::new(12, 10, 0x1f)
+    ]);
+
+    assert_coverage(&pixels, &[
+      "................",
+      "................",
+      "....########....",
+      "....########....",
+      "....########....",
+      "....########....",
+      "....########....",
+      "....########....",
+      "....########....",
+      "....########....",
+      "................",
+      "................"
+    ]);
+  }
+
+  #[test]
+  fn colors_are_interpolated_across_the_span() {
+    // the shallow right edge covers the right half of the first row, and isn't drawn for opaque polygons
+    let pixels = render(0, &[
+      TestVertex::new(2, 2, 0),
+      TestVertex::new(18, 2, 0x1f),
+      TestVertex::new(2, 4, 0)
+    ]);
+
+    // the span goes from black at x = 2 to full red at x = 18, with colors interpolated in 9 bits
+    let expected: Vec<Option<u8>> = (2..10).map(|x| Some(((0x1ff * (x - 2) / 16) >> 3) as u8)).collect();
+
+    assert_eq!(pixels[2][2..10], expected[..]);
+    assert_eq!(pixels[2][10..], [None; 6]);
+    assert!(pixels[3].iter().all(|pixel| pixel.is_none()));
+  }
+}
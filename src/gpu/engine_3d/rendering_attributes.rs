@@ -4,7 +4,17 @@ use serde::{Deserialize, Serialize};
 pub struct RenderingAttributes {
   pub is_translucent: bool,
   pub front_facing: bool,
-  pub fog_enabled: bool
+  pub fog_enabled: bool,
+  // id of the last opaque polygon drawn to the pixel
+  pub polygon_id: u32,
+  // whether the pixel lies on the outline of the opaque polygon drawn to it
+  pub is_edge: bool,
+  // how much of the pixel the opaque polygon covers (0-31), only less than 31 along its edges
+  pub coverage: u8,
+  // set by shadow mask polygons (polygon id 0) where they fail the depth test
+  pub stencil: bool,
+  // id of the last translucent polygon drawn to the pixel
+  pub translucent_polygon_id: u32
 }
 
 impl RenderingAttributes {
@@ -12,7 +22,12 @@ impl RenderingAttributes {
     Self {
       is_translucent: false,
       front_facing: false,
-      fog_enabled: false
+      fog_enabled: false,
+      polygon_id: 0,
+      is_edge: false,
+      coverage: 0x1f,
+      stencil: false,
+      translucent_polygon_id: 0
     }
   }
 }
\ No newline at end of file
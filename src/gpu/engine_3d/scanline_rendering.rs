@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use crate::gpu::{
+  registers::display_3d_control_register::Display3dControlRegister,
+  vram::VRam,
+  SCREEN_HEIGHT
+};
+
+use super::{polygon::Polygon, texture_cache::TextureKey, vertex::Vertex, Engine3d};
+
+/// How many lines the rendering engine can get ahead of the display.
+const LINE_BUFFER_SIZE: u32 = 48;
+
+/// The rendering engine runs at 33 MHz, so it gets 6 cycles for each of the 355 dots in a scanline.
+const CYCLES_PER_LINE: u32 = 355 * 6;
+
+/// Approximate cost of rendering a line: a fixed amount for clearing and outputting its
+/// pixels, plus setting up the edges of every polygon that crosses it.
+const LINE_CYCLES: u32 = 256;
+const POLYGON_LINE_CYCLES: u32 = 12;
+
+/*
+  Accuracy option that renders the frame the way the hardware does: a line at a time into a
+  48 line buffer, starting 48 lines before the first one gets displayed. Every scanline the
+  rendering engine gets more cycles to spend, and each line costs more the more polygons
+  cross it. Frames with a lot of polygons on the same lines can fall behind the display,
+  which is reported through the underflow flag in DISP3DCNT and the RDLINES_COUNT register.
+
+  Since lines are rendered (and post processed) when the engine gets to them, changes to
+  the rendering registers in the middle of a frame only affect the lines after that point.
+*/
+pub struct ScanlineFrame {
+  polygons: Vec<Polygon>,
+  vertices: Vec<Vertex>,
+  replacement_hashes: HashMap<TextureKey, u64>,
+  polygons_per_line: Vec<u32>,
+  // lines the rendering engine has gotten through at its own pace
+  engine_lines: u32,
+  // lines drawn to the frame buffer, which can be ahead of the engine when it has fallen behind the display
+  rendered_lines: u32,
+  displayed_lines: u32,
+  cycles: u32,
+  min_buffered_lines: u32
+}
+
+impl Engine3d {
+  pub fn start_scanline_frame(&mut self, vram: &VRam) {
+    let (polygons, vertices) = self.take_sorted_polygons();
+
+    let replacement_hashes = self.get_replacement_hashes(&polygons, vram);
+
+    let mut polygons_per_line = vec![0; SCREEN_HEIGHT as usize];
+
+    for polygon in &polygons {
+      for line in polygon.top..=polygon.bottom.min(SCREEN_HEIGHT as u32 - 1) {
+        polygons_per_line[line as usize] += 1;
+      }
+    }
+
+    self.scanline_frame = Some(ScanlineFrame {
+      polygons,
+      vertices,
+      replacement_hashes,
+      polygons_per_line,
+      engine_lines: 0,
+      rendered_lines: 0,
+      displayed_lines: 0,
+      cycles: 0,
+      min_buffered_lines: LINE_BUFFER_SIZE
+    });
+  }
+
+  /// Gives the rendering engine another scanline worth of cycles, and renders every line those
+  /// cycles pay for until the line buffer is full.
+  pub fn run_scanline(&mut self, vram: &VRam) {
+    let Some(frame) = &mut self.scanline_frame else {
+      return;
+    };
+
+    frame.cycles += CYCLES_PER_LINE;
+
+    let buffer_end = (frame.displayed_lines + LINE_BUFFER_SIZE).min(SCREEN_HEIGHT as u32);
+
+    let mut lines = frame.engine_lines;
+
+    while lines < buffer_end {
+      let cost = LINE_CYCLES + POLYGON_LINE_CYCLES * frame.polygons_per_line[lines as usize];
+
+      if frame.cycles < cost {
+        break;
+      }
+
+      frame.cycles -= cost;
+      lines += 1;
+    }
+
+    // the engine sits idle while the buffer is full
+    if lines == buffer_end {
+      frame.cycles = 0;
+    }
+
+    frame.engine_lines = lines;
+
+    self.render_scanlines(lines, vram);
+  }
+
+  /// Gets `line` ready to be displayed. If the rendering engine hasn't gotten to it yet the line
+  /// buffer has underflowed, which gets flagged, and the line is rendered right away.
+  pub fn start_display_line(&mut self, line: u16, vram: &VRam) {
+    let Some(frame) = &mut self.scanline_frame else {
+      return;
+    };
+
+    let line = line as u32;
+
+    // once the last line has been rendered there's nothing left to fall behind on
+    let buffered_lines = if frame.engine_lines == SCREEN_HEIGHT as u32 {
+      LINE_BUFFER_SIZE
+    } else {
+      frame.engine_lines.saturating_sub(line)
+    };
+
+    if buffered_lines == 0 {
+      self.disp3dcnt.insert(Display3dControlRegister::COLOR_BUFFER_UNDERFLOW_ACKNOWLEDGE);
+    }
+
+    frame.min_buffered_lines = frame.min_buffered_lines.min(buffered_lines);
+    frame.displayed_lines = line + 1;
+
+    // edge marking looks at the line below, so that one has to be there as well
+    self.render_scanlines((line + 2).min(SCREEN_HEIGHT as u32), vram);
+
+    let scale = self.resolution_scale() as u32;
+
+    self.apply_post_processing(line * scale..(line + 1) * scale);
+
+    if line == SCREEN_HEIGHT as u32 - 1 {
+      let frame = self.scanline_frame.take().unwrap();
+
+      self.rdlines_count = frame.min_buffered_lines.saturating_sub(1).min(46) as u8;
+    }
+  }
+
+  /// The minimum number of lines that were buffered at once during the last frame, minus one.
+  pub fn read_rdlines_count(&self) -> u16 {
+    self.rdlines_count as u16
+  }
+
+  fn render_scanlines(&mut self, end: u32, vram: &VRam) {
+    let Some(mut frame) = self.scanline_frame.take() else {
+      return;
+    };
+
+    if end > frame.rendered_lines {
+      let scale = self.resolution_scale() as u32;
+
+      let rows = frame.rendered_lines * scale..end * scale;
+
+      self.clear_rows(rows.clone(), vram);
+      self.render_polygons(&frame.polygons, &frame.vertices, &frame.replacement_hashes, rows, vram);
+
+      frame.rendered_lines = end;
+    }
+
+    self.scanline_frame = Some(frame);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::gpu::{
+    color::Color,
+    engine_3d::test_scene::{self, TestVertex},
+    registers::display_3d_control_register::Display3dControlRegister,
+    vram::VRam,
+    SCREEN_HEIGHT,
+    SCREEN_WIDTH
+  };
+
+  use super::Engine3d;
+
+  /// Renders the vertices as separate triangles a line at a time, running the rendering engine
+  /// through vblank and then every displayed line. `before_line` gets called before each line
+  /// is displayed.
+  fn render_frame(vertices: &[TestVertex], mut before_line: impl FnMut(&mut Engine3d, u16)) -> Engine3d {
+    let mut engine = Engine3d::new();
+
+    engine.scanline_rendering = true;
+
+    engine.write_clear_color(0x1f << 16 | 0x7fff);
+
+    test_scene::render(&mut engine, &test_scene::frame_commands(0x1f << 16 | 0xc0, 0, vertices));
+
+    let vram = VRam::new();
+
+    for _ in 0..48 {
+      engine.run_scanline(&vram);
+    }
+
+    for line in 0..SCREEN_HEIGHT {
+      before_line(&mut engine, line);
+
+      engine.start_display_line(line, &vram);
+      engine.run_scanline(&vram);
+    }
+
+    engine
+  }
+
+  fn line_color(engine: &Engine3d, line: u32) -> Option<Color> {
+    engine.frame_buffer[(SCREEN_WIDTH as u32 - 1 + line * SCREEN_WIDTH as u32) as usize].color
+  }
+
+  #[test]
+  fn light_frames_keep_the_line_buffer_full() {
+    let engine = render_frame(&[
+      TestVertex::new(10, 10, 0),
+      TestVertex::new(100, 10, 0),
+      TestVertex::new(10, 100, 0)
+    ], |_, _| {});
+
+    // lines past the ones rendered during vblank still get the polygon
+    assert_eq!(engine.frame_buffer[12 + 90 * SCREEN_WIDTH as usize].color.map(|color| color.r), Some(0));
+
+    assert!(!engine.disp3dcnt.contains(Display3dControlRegister::COLOR_BUFFER_UNDERFLOW_ACKNOWLEDGE));
+    assert_eq!(engine.read_rdlines_count(), 46);
+  }
+
+  #[test]
+  fn too_many_polygons_on_a_line_underflow_the_line_buffer() {
+    // thin triangles covering every line of the screen
+    let vertices: Vec<TestVertex> = (0..400).flat_map(|i| {
+      let x = i % 250;
+
+      [
+        TestVertex::new(x, 0, 0),
+        TestVertex::new(x + 2, 0, 0),
+        TestVertex::new(x, SCREEN_HEIGHT as u32 - 1, 0)
+      ]
+    }).collect();
+
+    let engine = render_frame(&vertices, |_, _| {});
+
+    assert!(engine.disp3dcnt.contains(Display3dControlRegister::COLOR_BUFFER_UNDERFLOW_ACKNOWLEDGE));
+    assert!(engine.read_rdlines_count() < 46, "{}", engine.read_rdlines_count());
+  }
+
+  #[test]
+  fn clear_color_changes_only_affect_lines_rendered_afterwards() {
+    let mut changed_at = 0;
+
+    let engine = render_frame(&[
+      TestVertex::new(10, 10, 0),
+      TestVertex::new(100, 10, 0),
+      TestVertex::new(10, 100, 0)
+    ], |engine, line| {
+      if line == 100 {
+        changed_at = engine.scanline_frame.as_ref().unwrap().rendered_lines;
+
+        engine.write_clear_color(0x1f << 16 | 0x1f);
+      }
+    });
+
+    assert!(changed_at > 100 && changed_at < SCREEN_HEIGHT as u32, "{changed_at}");
+
+    let white = line_color(&engine, 0);
+    let red = line_color(&engine, SCREEN_HEIGHT as u32 - 1);
+
+    assert_ne!(white, red);
+
+    for line in 0..SCREEN_HEIGHT as u32 {
+      let expected = if line < changed_at { white } else { red };
+
+      assert_eq!(line_color(&engine, line), expected, "line {line}");
+    }
+  }
+}
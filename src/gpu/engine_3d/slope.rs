@@ -0,0 +1,209 @@
+use super::{interpolator::Interpolator, vertex::Vertex};
+
+/*
+  A polygon edge stepped one scanline at a time, using the same 18 bit fixed point
+  position as the hardware. Edges that move more than a pixel per scanline are
+  X-major and cover a run of pixels on each scanline, the others are Y-major and
+  only ever cover one.
+*/
+pub struct Slope {
+  x0: i32,
+  x_min: i32,
+  x_max: i32,
+  x_len: i32,
+  y_len: i32,
+  y: i32,
+  dx: i32,
+  increment: i32,
+  negative: bool,
+  x_major: bool,
+  is_right: bool,
+  interpolator: Interpolator,
+  start: Vertex,
+  end: Vertex
+}
+
+/// Every value that gets interpolated along an edge and then across the span.
+/// Colors have 9 bits of precision, texcoords keep their 4 fractional bits.
+#[derive(Copy, Clone)]
+pub struct EdgePoint {
+  pub x: i32,
+  pub w: i32,
+  pub z: u32,
+  pub u: i32,
+  pub v: i32,
+  pub r: i32,
+  pub g: i32,
+  pub b: i32
+}
+
+impl Slope {
+  pub fn new(start: Vertex, end: Vertex, y: u32, is_right: bool) -> Self {
+    let x0 = start.screen_x as i32;
+    let x1 = end.screen_x as i32;
+
+    let (x_min, x_max, negative) = if x1 > x0 {
+      (x0, x1 - 1, false)
+    } else if x1 < x0 {
+      (x1, x0 - 1, true)
+    } else {
+      // vertical right edges end just before the vertex
+      let x = if is_right { x0 - 1 } else { x0 };
+
+      (x, x, false)
+    };
+
+    let x_len = x_max + 1 - x_min;
+    let y_len = end.screen_y as i32 - start.screen_y as i32;
+
+    let increment = if y_len == 0 {
+      0
+    } else if y_len == x_len && x_len != 1 {
+      0x40000
+    } else {
+      ((x1 - x0) * ((1 << 18) / y_len)).abs()
+    };
+
+    let x_major = increment > 0x40000;
+
+    // X-major edges start half a pixel in so the run is centered on the scanline
+    let dx = match (is_right, x_major, negative) {
+      (false, true, false) => 0x20000,
+      (false, true, true) => increment - 0x20000 + 0x40000,
+      (true, true, false) => increment - 0x20000,
+      (true, true, true) => 0x20000 + 0x40000,
+      (_, false, true) if increment != 0 => 0x40000,
+      _ => 0
+    };
+
+    let w0 = start.normalized_w as u16 as i32;
+    let w1 = end.normalized_w as u16 as i32;
+
+    let mut slope = Self {
+      x0,
+      x_min,
+      x_max,
+      x_len,
+      y_len,
+      y: y as i32,
+      dx: dx + (y as i32 - start.screen_y as i32) * increment,
+      increment,
+      negative,
+      x_major,
+      is_right,
+      interpolator: Interpolator::new(start.screen_y as i32, end.screen_y as i32, w0, w1, true),
+      start,
+      end
+    };
+
+    slope.interpolator.set_x(slope.y);
+
+    slope
+  }
+
+  /// Flat polygons have no real edges, their leftmost and rightmost vertices are used instead.
+  pub fn new_flat(vertex: Vertex, y: u32, is_right: bool) -> Self {
+    Self::new(vertex, vertex, y, is_right)
+  }
+
+  pub fn step(&mut self) {
+    self.dx += self.increment;
+    self.y += 1;
+
+    self.interpolator.set_x(self.y);
+  }
+
+  /// The pixel the edge starts at on the current scanline, for right edges this is the last pixel drawn.
+  pub fn x(&self) -> i32 {
+    let x = if self.negative {
+      self.x0 - (self.dx >> 18)
+    } else {
+      self.x0 + (self.dx >> 18)
+    };
+
+    x.clamp(self.x_min, self.x_max)
+  }
+
+  /// How many pixels the edge covers on the current scanline.
+  pub fn length(&self) -> i32 {
+    if !self.x_major {
+      1
+    } else if self.is_right ^ self.negative {
+      (self.dx >> 18) - ((self.dx - self.increment) >> 18)
+    } else {
+      ((self.dx + self.increment) >> 18) - (self.dx >> 18)
+    }
+  }
+
+  /// Whether the hardware draws this edge for opaque polygons. Left edges are drawn unless
+  /// they're X-major and slope to the right, right edges only when they're vertical or
+  /// X-major and slope to the right.
+  pub fn is_filled(&self) -> bool {
+    if self.is_right {
+      self.increment == 0 || (self.x_major && !self.negative)
+    } else {
+      !self.x_major || self.negative
+    }
+  }
+
+  /// Returns how much of pixel `x` (0-31) is on the inside of the edge.
+  pub fn coverage(&self, x: i32) -> u8 {
+    if self.increment == 0 {
+      return 0x1f;
+    }
+
+    let coverage = if self.x_major {
+      // the fraction of the scanline the edge has gone through by the middle of the pixel
+      let offset = if self.negative { self.x0 - 1 - x } else { x - self.x0 };
+
+      (((((offset << 10) + 0x1ff) as i64 * self.y_len as i64) / self.x_len as i64) as i32 & 0x3ff) >> 5
+    } else {
+      // where the edge crosses the pixel halfway down the scanline
+      ((self.dx + self.increment / 2) >> 13) & 0x1f
+    };
+
+    let coverage = if self.x_major == (self.is_right ^ self.negative) {
+      0x1f - coverage
+    } else {
+      coverage
+    };
+
+    coverage as u8
+  }
+
+  pub fn edge_point(&self, w_buffer: bool) -> EdgePoint {
+    let (start, end) = (self.start, self.end);
+
+    let interpolate = |y0: i32, y1: i32| self.interpolator.interpolate(y0, y1);
+
+    // vertex colors are 6 bit, the hardware interpolates them with 9 bits
+    let color = |c: u8| {
+      let c = c as i32;
+
+      if c == 0 { 0 } else { (c << 3) | 0x7 }
+    };
+
+    EdgePoint {
+      x: self.x(),
+      w: interpolate(start.normalized_w as u16 as i32, end.normalized_w as u16 as i32),
+      z: self.interpolator.interpolate_depth(
+        Self::get_vertex_depth(start, w_buffer),
+        Self::get_vertex_depth(end, w_buffer),
+        w_buffer
+      ),
+      u: interpolate(start.texcoord.u as i32, end.texcoord.u as i32),
+      v: interpolate(start.texcoord.v as i32, end.texcoord.v as i32),
+      r: interpolate(color(start.color.r), color(end.color.r)),
+      g: interpolate(color(start.color.g), color(end.color.g)),
+      b: interpolate(color(start.color.b), color(end.color.b))
+    }
+  }
+
+  fn get_vertex_depth(vertex: Vertex, w_buffer: bool) -> u32 {
+    if w_buffer {
+      vertex.transformed[3].clamp(0, 0xff_ffff) as u32
+    } else {
+      vertex.z_depth
+    }
+  }
+}
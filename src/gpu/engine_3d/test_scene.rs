@@ -0,0 +1,76 @@
+use crate::gpu::vram::VRam;
+
+use super::{Command, Engine3d, GeometryCommandEntry};
+
+/*
+  Helpers for tests that draw polygons through the geometry engine. Matrices are left as the
+  identity and the viewport covers the whole screen, so vertices can be given in screen pixels.
+*/
+pub struct TestVertex {
+  pub x: u32,
+  pub y: u32,
+  // RGB555
+  pub color: u16
+}
+
+impl TestVertex {
+  pub fn new(x: u32, y: u32, color: u16) -> Self {
+    Self {
+      x,
+      y,
+      color
+    }
+  }
+
+  /// Inverts the viewport transform, rounding up so the vertex lands exactly on the pixel.
+  fn position(&self) -> (i16, i16) {
+    let x = (self.x * 0x2000).div_ceil(255) as i32 - 0x1000;
+    let y = 0x1000 - (self.y * 0x2000).div_ceil(191) as i32;
+
+    (x as i16, y as i16)
+  }
+}
+
+/// Commands for one frame made of a single primitive. `polygon_attr` is written as is, so it
+/// needs to at least include an alpha and the sides to show.
+pub fn frame_commands(polygon_attr: u32, primitive_type: u32, vertices: &[TestVertex]) -> Vec<GeometryCommandEntry> {
+  use Command::*;
+
+  let mut commands = vec![
+    (Viewport, 0xbfff_0000),
+    (MtxMode, 0),
+    (MtxIdentity, 0),
+    (MtxMode, 2),
+    (MtxIdentity, 0),
+    (PolygonAttr, polygon_attr),
+    (TexImageParam, 0),
+    (BeginVtxs, primitive_type)
+  ];
+
+  for vertex in vertices {
+    let (x, y) = vertex.position();
+
+    commands.push((Color, vertex.color as u32));
+    commands.push((Vtx16, x as u16 as u32 | (y as u16 as u32) << 16));
+    commands.push((Vtx16, 0));
+  }
+
+  commands.push((EndVtxs, 0));
+  commands.push((SwapBuffers, 0));
+
+  commands
+    .into_iter()
+    .map(|(command, param)| GeometryCommandEntry::from(command, param))
+    .collect()
+}
+
+/// Runs the commands and renders the frame, everything ends up in front of the clear plane.
+pub fn render(engine: &mut Engine3d, commands: &[GeometryCommandEntry]) {
+  engine.write_clear_depth(0x7fff);
+
+  for entry in commands {
+    engine.execute_command(*entry);
+  }
+
+  engine.start_rendering(&VRam::new());
+}
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::gpu::{color::Color, vram::VRam};
+
+use super::{
+  polygon::Polygon,
+  texture_params::{TextureFormat, TextureParams},
+  Engine3d
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01b3;
+
+pub struct DecodedTexture {
+  pub hash: u64,
+  pub width: u32,
+  pub height: u32,
+  pub format: TextureFormat,
+  pub vram_offset: u32,
+  pub palette_base: u32,
+  // both of these are in RGBA8 format
+  pub pixels: Vec<u8>,
+  pub palette: Vec<u8>
+}
+
+impl DecodedTexture {
+  pub fn decode(tex_params: &TextureParams, palette_base: u32, vram: &VRam) -> Self {
+    let width = tex_params.texture_s_size;
+    let height = tex_params.texture_t_size;
+
+    let mut pixels = vec![0; (4 * width * height) as usize];
+
+    for v in 0..height {
+      for u in 0..width {
+        let index = 4 * (u + v * width) as usize;
+
+        if let Some(color) = Engine3d::get_texel_color(tex_params, palette_base as usize, u as i32, v as i32, vram) {
+          Self::write_rgba(&mut pixels[index..index + 4], color);
+        }
+      }
+    }
+
+    let mut palette = Vec::new();
+
+    for i in 0..Self::palette_entries(tex_params.texture_format) {
+      let color_raw = vram.read_texture_palette::<u16>(Self::palette_address(tex_params.texture_format, palette_base, i));
+
+      let mut color = Color::from(color_raw).to_rgb6();
+      color.alpha = Some(0x1f);
+
+      let mut entry = [0; 4];
+
+      Self::write_rgba(&mut entry, color);
+
+      palette.extend_from_slice(&entry);
+    }
+
+    Self {
+      hash: Self::get_hash(tex_params, palette_base, vram),
+      width,
+      height,
+      format: tex_params.texture_format,
+      vram_offset: tex_params.vram_offset,
+      palette_base,
+      pixels,
+      palette
+    }
+  }
+
+  /// Hashes the raw texel data and the palette colors it refers to. Two textures with the same
+  /// hash will decode to the same image regardless of where in vram they're stored.
+  pub fn get_hash(tex_params: &TextureParams, palette_base: u32, vram: &VRam) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+
+    let mut add_byte = |byte: u8| {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(FNV_PRIME);
+    };
+
+    let width = tex_params.texture_s_size;
+    let height = tex_params.texture_t_size;
+
+    for byte in (width as u16).to_le_bytes().iter().chain((height as u16).to_le_bytes().iter()) {
+      add_byte(*byte);
+    }
+
+    add_byte(tex_params.texture_format as u8);
+    add_byte(tex_params.color0_transparent as u8);
+
+    let texel_bytes = match tex_params.texture_format {
+      TextureFormat::None => 0,
+      TextureFormat::Color4 | TextureFormat::Color4x4 => width * height / 4,
+      TextureFormat::Color16 => width * height / 2,
+      TextureFormat::A3I5Translucent | TextureFormat::A5I3Translucent | TextureFormat::Color256 => width * height,
+      TextureFormat::Direct => width * height * 2
+    };
+
+    for i in 0..texel_bytes {
+      add_byte(vram.read_texture::<u8>(tex_params.vram_offset + i));
+    }
+
+    if tex_params.texture_format == TextureFormat::Color4x4 {
+      // each 4x4 block has an additional halfword in slot 1 that selects its palette and mode
+      for block in 0..width * height / 16 {
+        let base_address = tex_params.vram_offset + 4 * block;
+        let palette_info = vram.read_texture::<u16>(Engine3d::get_4x4_palette_info_address(base_address));
+
+        for byte in palette_info.to_le_bytes() {
+          add_byte(byte);
+        }
+
+        let palette_offset = palette_base + (palette_info as u32 & 0x3fff) * 4;
+
+        for i in 0..4 {
+          for byte in vram.read_texture_palette::<u16>(palette_offset + 2 * i).to_le_bytes() {
+            add_byte(byte);
+          }
+        }
+      }
+    }
+
+    for i in 0..Self::palette_entries(tex_params.texture_format) {
+      for byte in vram.read_texture_palette::<u16>(Self::palette_address(tex_params.texture_format, palette_base, i)).to_le_bytes() {
+        add_byte(byte);
+      }
+    }
+
+    hash
+  }
+
+  fn palette_entries(format: TextureFormat) -> u32 {
+    match format {
+      TextureFormat::Color4 => 4,
+      TextureFormat::A5I3Translucent => 8,
+      TextureFormat::Color16 => 16,
+      TextureFormat::A3I5Translucent => 32,
+      TextureFormat::Color256 => 256,
+      // 4x4 compressed textures use a different palette per block, direct textures have no palette
+      TextureFormat::Color4x4 | TextureFormat::Direct | TextureFormat::None => 0
+    }
+  }
+
+  fn palette_address(format: TextureFormat, palette_base: u32, index: u32) -> u32 {
+    if format == TextureFormat::Color4 {
+      palette_base / 2 + index * 2
+    } else {
+      palette_base + index * 2
+    }
+  }
+
+  fn write_rgba(buffer: &mut [u8], color: Color) {
+    // colors are 6 bits per channel at this point
+    buffer[0] = (color.r << 2) | (color.r >> 4);
+    buffer[1] = (color.g << 2) | (color.g >> 4);
+    buffer[2] = (color.b << 2) | (color.b >> 4);
+
+    let alpha = color.alpha.unwrap_or(0x1f);
+
+    buffer[3] = (alpha << 3) | (alpha >> 2);
+  }
+}
+
+/// A user supplied image that is sampled in place of the texture with a matching hash.
+/// It can be any size, texture coordinates are scaled from the original texture's size.
+#[derive(Clone)]
+pub struct ReplacementTexture {
+  pub width: u32,
+  pub height: u32,
+  texels: Vec<Color>
+}
+
+impl ReplacementTexture {
+  pub fn new(width: u32, height: u32, rgba: &[u8]) -> Self {
+    let texels = rgba
+      .chunks(4)
+      .take((width * height) as usize)
+      .map(|pixel| Color {
+        r: pixel[0] >> 2,
+        g: pixel[1] >> 2,
+        b: pixel[2] >> 2,
+        alpha: Some(pixel[3] >> 3)
+      })
+      .collect();
+
+    Self {
+      width,
+      height,
+      texels
+    }
+  }
+
+  /// `s` and `t` are the interpolated texture coordinates with their 4 fractional bits intact,
+  /// which lets larger replacements show detail that falls between the original texels.
+  pub fn get_texel_color(&self, tex_params: &TextureParams, s: i32, t: i32) -> Option<Color> {
+    let u = Self::wrap(
+      (s as i64 * self.width as i64).div_euclid(16 * tex_params.texture_s_size as i64),
+      self.width as i64,
+      tex_params.repeat_s,
+      tex_params.flip_s
+    );
+
+    let v = Self::wrap(
+      (t as i64 * self.height as i64).div_euclid(16 * tex_params.texture_t_size as i64),
+      self.height as i64,
+      tex_params.repeat_t,
+      tex_params.flip_t
+    );
+
+    self.texels.get((u + v * self.width) as usize).copied()
+  }
+
+  fn wrap(val: i64, size: i64, repeat: bool, flip: bool) -> u32 {
+    if repeat {
+      let wrapped = val.rem_euclid(size);
+
+      if flip && val.div_euclid(size) % 2 != 0 {
+        (size - 1 - wrapped) as u32
+      } else {
+        wrapped as u32
+      }
+    } else {
+      val.clamp(0, size - 1) as u32
+    }
+  }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TextureKey {
+  vram_offset: u32,
+  palette_base: usize,
+  format: u8,
+  width: u32,
+  height: u32,
+  color0_transparent: bool
+}
+
+impl TextureKey {
+  pub fn new(polygon: &Polygon) -> Self {
+    Self {
+      vram_offset: polygon.tex_params.vram_offset,
+      palette_base: polygon.palette_base,
+      format: polygon.tex_params.texture_format as u8,
+      width: polygon.tex_params.texture_s_size,
+      height: polygon.tex_params.texture_t_size,
+      color0_transparent: polygon.tex_params.color0_transparent
+    }
+  }
+}
+
+impl Engine3d {
+  pub fn add_texture_replacement(&mut self, hash: u64, width: u32, height: u32, rgba: &[u8]) {
+    Arc::make_mut(&mut self.texture_replacements).insert(hash, ReplacementTexture::new(width, height, rgba));
+  }
+
+  pub fn clear_texture_replacements(&mut self) {
+    self.texture_replacements = Arc::new(HashMap::new());
+  }
+
+  /// Finds the hashes of the textures used by the frame that a replacement exists for. Each texture
+  /// only gets hashed again once texture memory has changed.
+  pub fn get_replacement_hashes(&mut self, polygons: &[Polygon], vram: &VRam) -> HashMap<TextureKey, u64> {
+    let mut replacement_hashes = HashMap::new();
+
+    if self.texture_replacements.is_empty() {
+      return replacement_hashes;
+    }
+
+    if self.texture_hashes_generation != vram.texture_generation {
+      self.texture_hashes.clear();
+      self.texture_hashes_generation = vram.texture_generation;
+    }
+
+    for polygon in polygons {
+      if polygon.tex_params.texture_format == TextureFormat::None {
+        continue;
+      }
+
+      let key = TextureKey::new(polygon);
+
+      let hash = *self.texture_hashes
+        .entry(key)
+        .or_insert_with(|| DecodedTexture::get_hash(&polygon.tex_params, polygon.palette_base as u32, vram));
+
+      if self.texture_replacements.contains_key(&hash) {
+        replacement_hashes.insert(key, hash);
+      }
+    }
+
+    replacement_hashes
+  }
+
+  pub fn get_frame_textures(polygons: &[Polygon], vram: &VRam) -> Vec<DecodedTexture> {
+    let mut textures: Vec<DecodedTexture> = Vec::new();
+
+    for polygon in polygons {
+      let tex_params = &polygon.tex_params;
+
+      if tex_params.texture_format == TextureFormat::None {
+        continue;
+      }
+
+      let palette_base = polygon.palette_base as u32;
+
+      let already_decoded = textures.iter().any(|texture| {
+        texture.vram_offset == tex_params.vram_offset &&
+          texture.palette_base == palette_base &&
+          texture.format == tex_params.texture_format &&
+          texture.width == tex_params.texture_s_size &&
+          texture.height == tex_params.texture_t_size
+      });
+
+      if !already_decoded {
+        textures.push(DecodedTexture::decode(tex_params, palette_base, vram));
+      }
+    }
+
+    textures
+  }
+}
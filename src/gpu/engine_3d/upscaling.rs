@@ -0,0 +1,106 @@
+use crate::gpu::{color::Color, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use super::{rendering_attributes::RenderingAttributes, vertex::Vertex, Engine3d, Pixel3d};
+
+pub const MAX_RESOLUTION_SCALE: usize = 4;
+
+/// The scale a 3D frame buffer of `len` pixels was allocated at.
+pub fn frame_scale(len: usize) -> usize {
+  let ratio = len / (SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize);
+
+  (1..=MAX_RESOLUTION_SCALE).find(|scale| scale * scale == ratio).unwrap_or(1)
+}
+
+/*
+  Enhancement that rasterizes polygons at a multiple of the native resolution. The frame buffer
+  grows by the scale in both directions and vertices are placed using their subpixel screen
+  positions, everything else (texturing, depth, post processing) works the same per pixel.
+
+  Anything that has to see the image the hardware would have produced, like display capture,
+  reads it back through `get_native_color`, which downsamples each block of pixels.
+*/
+impl Engine3d {
+  /// The scale is derived from the size of the frame buffer, so it always matches what's in it,
+  /// even right after loading a save state.
+  pub fn resolution_scale(&self) -> usize {
+    frame_scale(self.frame_buffer.len())
+  }
+
+  pub fn set_resolution_scale(&mut self, scale: usize) {
+    let scale = scale.clamp(1, MAX_RESOLUTION_SCALE);
+
+    if scale == self.resolution_scale() {
+      return;
+    }
+
+    // a frame that's still being rendered on the worker thread has the old size, as do the
+    // vertices of a frame being rendered a few lines at a time, which just gets dropped
+    self.finish_rendering();
+
+    self.scanline_frame = None;
+
+    let len = SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize * scale * scale;
+
+    self.frame_buffer = vec![Pixel3d::new(); len].into_boxed_slice();
+    self.attributes_buffer = vec![RenderingAttributes::new(); len].into_boxed_slice();
+  }
+
+  /// Returns the color of the native resolution pixel at `address`, averaging every rendered
+  /// pixel inside of it. Pixels that are mostly empty are treated as empty.
+  pub fn get_native_color(&self, address: usize) -> Option<Color> {
+    let scale = self.resolution_scale();
+
+    if scale == 1 {
+      return self.frame_buffer[address].color;
+    }
+
+    let x = (address % SCREEN_WIDTH as usize) * scale;
+    let y = (address / SCREEN_WIDTH as usize) * scale;
+
+    let width = SCREEN_WIDTH as usize * scale;
+
+    let mut sum = [0u32; 4];
+    let mut count = 0;
+
+    for sub_y in 0..scale {
+      for sub_x in 0..scale {
+        if let Some(color) = self.frame_buffer[x + sub_x + (y + sub_y) * width].color {
+          sum[0] += color.r as u32;
+          sum[1] += color.g as u32;
+          sum[2] += color.b as u32;
+          sum[3] += color.alpha.unwrap_or(0x1f) as u32;
+
+          count += 1;
+        }
+      }
+    }
+
+    if 2 * count < scale * scale {
+      return None;
+    }
+
+    let count = count as u32;
+
+    Some(Color {
+      r: (sum[0] / count) as u8,
+      g: (sum[1] / count) as u8,
+      b: (sum[2] / count) as u8,
+      alpha: Some((sum[3] / count) as u8)
+    })
+  }
+
+  /// Moves every vertex to its position in the upscaled frame buffer.
+  pub fn get_scaled_vertices(vertices: &[Vertex], scale: u32) -> Vec<Vertex> {
+    vertices
+      .iter()
+      .map(|vertex| {
+        let mut vertex = *vertex;
+
+        vertex.screen_x = (vertex.subpixel_x * scale) >> 4;
+        vertex.screen_y = (vertex.subpixel_y * scale) >> 4;
+
+        vertex
+      })
+      .collect()
+  }
+}
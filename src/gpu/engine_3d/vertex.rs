@@ -8,6 +8,9 @@ use super::texcoord::Texcoord;
 pub struct Vertex {
   pub screen_x: u32,
   pub screen_y: u32,
+  /// Screen position with 4 fractional bits, used to place vertices when rendering above native resolution.
+  pub subpixel_x: u32,
+  pub subpixel_y: u32,
   pub z_depth: u32,
   pub x: i16,
   pub y: i16,
@@ -23,6 +26,8 @@ impl Vertex {
     Self {
       screen_x: 0,
       screen_y: 0,
+      subpixel_x: 0,
+      subpixel_y: 0,
       z_depth: 0,
       x: 0,
       y: 0,
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+  registers::{display_control_register::DisplayControlRegisterFlags, power_control_register1::PowerControlRegister1},
+  GPU
+};
+
+/// How many frames in a row 3D has to be shown on the other screen before the hint follows it.
+/// Games that render 3D on both screens swap them every frame, which shouldn't move the hint.
+const SWITCH_FRAMES: u8 = 30;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Screen {
+  Top,
+  Bottom
+}
+
+/*
+  Guesses which physical screen holds the action, so frontends can enlarge it in hybrid
+  layouts or pick it in single screen mode. The screen showing engine A's 3D layer wins,
+  otherwise touching the screen moves the hint to the bottom one. Without either, the hint
+  stays where it was.
+*/
+#[derive(Serialize, Deserialize)]
+pub struct MainScreenHint {
+  pub screen: Screen,
+  candidate_frames: u8
+}
+
+impl MainScreenHint {
+  pub fn new() -> Self {
+    Self {
+      screen: Screen::Top,
+      candidate_frames: 0
+    }
+  }
+}
+
+impl Default for MainScreenHint {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GPU {
+  /// Updates the hint once the frame has been displayed.
+  pub fn update_main_screen_hint(&mut self, pen_down: bool) {
+    let engine_a_screen = if self.powcnt1.contains(PowerControlRegister1::TOP_A) { Screen::Top } else { Screen::Bottom };
+
+    let has_3d = self.powcnt1.contains(PowerControlRegister1::ENGINE_3D_ENABLE) &&
+      self.engine_a.is_bg0_3d() &&
+      self.engine_a.dispcnt.flags.contains(DisplayControlRegisterFlags::DISPLAY_BG0);
+
+    let hint = &mut self.main_screen_hint;
+
+    if has_3d {
+      if engine_a_screen == hint.screen {
+        hint.candidate_frames = 0;
+      } else {
+        hint.candidate_frames += 1;
+
+        if hint.candidate_frames >= SWITCH_FRAMES {
+          hint.screen = engine_a_screen;
+          hint.candidate_frames = 0;
+        }
+      }
+    } else if pen_down {
+      hint.screen = Screen::Bottom;
+      hint.candidate_frames = 0;
+    }
+  }
+}
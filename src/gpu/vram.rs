@@ -37,6 +37,23 @@ impl Bank {
   }
 }
 
+/// The parts of the address space that VRAM banks can be mapped to.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum VramRegion {
+  Lcdc,
+  EngineABg,
+  EngineAObj,
+  EngineBBg,
+  EngineBObj,
+  Arm7Wram,
+  EngineABgExtendedPalette,
+  EngineBBgExtendedPalette,
+  EngineAObjExtendedPalette,
+  EngineBObjExtendedPalette,
+  Textures,
+  TexturePalette
+}
+
 const ENGINE_A_OBJ_BLOCKS: usize = 256 / 16;
 const ENGINE_A_BG_BLOCKS: usize = 512 / 16;
 const ENGINE_B_BG_BLOCKS: usize = 128 / 16;
@@ -50,7 +67,7 @@ const ARM7_WRAM_BLOCKS: usize = 2;
 
 const BLOCK_SIZE: usize = 16 * 1024;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct VRam {
   pub banks: [Vec<u8>; 9],
   pub lcdc: HashSet<Bank>,
@@ -64,7 +81,11 @@ pub struct VRam {
   engine_a_obj_extended_palette: Vec<HashSet<Bank>>,
   engine_b_obj_extended_palette: Vec<HashSet<Bank>>,
   textures: Vec<HashSet<Bank>>,
-  texture_palette: Vec<HashSet<Bank>>
+  texture_palette: Vec<HashSet<Bank>>,
+  /// Changes whenever texture or texture palette memory could have changed. Banks mapped there
+  /// can't be written by the CPUs, so that only happens when banks are mapped in or out.
+  #[serde(skip)]
+  pub texture_generation: u32
 }
 
 pub const BANK_SIZES: [usize; 9] = [
@@ -104,7 +125,8 @@ impl VRam {
       engine_a_obj_extended_palette: Self::create_vec(ENGINE_A_EXTENDED_OBJ_PALETTE_BLOCKS),
       engine_b_obj_extended_palette: Self::create_vec(ENGINE_B_EXTENDED_OBJ_PALETTE_BLOCKS),
       textures: Self::create_vec(TEXTURE_BLOCKS),
-      texture_palette: Self::create_vec(TEXTURE_PALETTE_BLOCKS)
+      texture_palette: Self::create_vec(TEXTURE_PALETTE_BLOCKS),
+      texture_generation: 0
     }
   }
 
@@ -275,195 +297,257 @@ impl VRam {
     Self::read_mapping::<T>(&self.banks, &self.textures, TEXTURE_BLOCKS - 1, address)
   }
 
-  pub fn map_bank(&mut self, bank: Bank, vramcnt: &VramControlRegister) {
-    let mut size = BANK_SIZES[bank as usize];
-    match vramcnt.vram_mst {
-      0 => {
-        self.lcdc.insert(bank);
-      }
-      1 => match bank {
-        Bank::BankA | Bank::BankB | Bank::BankC | Bank::BankD => {
-          let offset = 0x2_0000 * vramcnt.vram_offset as usize;
+  /// Copies over the banks mapped to texture and texture palette memory, which is all the 3D
+  /// renderer reads. The contents of the other banks are left as they were.
+  pub fn copy_texture_banks(&mut self, other: &VRam) {
+    self.textures.clone_from(&other.textures);
+    self.texture_palette.clone_from(&other.texture_palette);
+    self.texture_generation = other.texture_generation;
 
-          Self::add_mapping(&mut self.engine_a_bg, bank, size, offset);
-        }
-        Bank::BankE => Self::add_mapping(&mut self.engine_a_bg, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          //(4000h*OFS.0)+(10000h*OFS.1)
-          let offset = 0x4000 * ((vramcnt.vram_offset as usize) & 0x1) + 0x10000 * (((vramcnt.vram_offset as usize) >> 1) & 0x1);
-          Self::add_mapping(&mut self.engine_a_bg, bank, size, offset);
-        }
-        Bank::BankH => Self::add_mapping(&mut self.engine_b_bg, bank, size, 0),
-        Bank::BankI => Self::add_mapping(&mut self.engine_b_bg, bank, size, 0)
+    let mut copied = HashSet::new();
+
+    for bank in self.textures.iter().chain(self.texture_palette.iter()).flatten() {
+      if copied.insert(*bank) {
+        self.banks[*bank as usize].copy_from_slice(&other.banks[*bank as usize]);
       }
-      2 => match bank {
-        Bank::BankA | Bank::BankB  => {
-          let offset = 0x20000 * ((vramcnt.vram_offset as usize) & 0x1);
+    }
+  }
 
-          Self::add_mapping(&mut self.engine_a_obj, bank, size, offset);
-        }
-        Bank::BankE => Self::add_mapping(&mut self.engine_a_obj, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          let offset = 0x4000 * (vramcnt.vram_offset as usize & 0x1) + 0x10000 * (((vramcnt.vram_offset as usize) >> 1) & 0x1);
+  /// Returns where a bank shows up for the given VRAMCNT settings, along with the size and offset
+  /// of the mapping within that region. Combinations that GBATEK lists as invalid don't map the bank anywhere.
+  pub fn get_bank_mapping(bank: Bank, mst: u8, offset: u8) -> Option<(VramRegion, usize, usize)> {
+    use Bank::*;
+    use VramRegion::*;
+
+    let size = BANK_SIZES[bank as usize];
+    let ofs = offset as usize;
+
+    // banks F and G move in 16K steps, with OFS.1 skipping ahead 64K
+    let small_bank_offset = 0x4000 * (ofs & 0b1) + 0x1_0000 * ((ofs >> 1) & 0b1);
+
+    let mapping = match (mst, bank) {
+      (0, _) => (Lcdc, size, 0),
+      (1, BankA | BankB | BankC | BankD) => (EngineABg, size, 0x2_0000 * ofs),
+      (1, BankE) => (EngineABg, size, 0),
+      (1, BankF | BankG) => (EngineABg, size, small_bank_offset),
+      (1, BankH) => (EngineBBg, size, 0),
+      (1, BankI) => (EngineBBg, size, 0x8000),
+      (2, BankA | BankB) => (EngineAObj, size, 0x2_0000 * (ofs & 0b1)),
+      (2, BankC | BankD) => (Arm7Wram, size, 0x2_0000 * (ofs & 0b1)),
+      (2, BankE) => (EngineAObj, size, 0),
+      (2, BankF | BankG) => (EngineAObj, size, small_bank_offset),
+      (2, BankH) => (EngineBBgExtendedPalette, size, 0),
+      (2, BankI) => (EngineBObj, size, 0),
+      (3, BankA | BankB | BankC | BankD) => (Textures, size, 0x2_0000 * ofs),
+      (3, BankE) => (TexturePalette, size, 0),
+      // slot OFS.0 + OFS.1 * 4
+      (3, BankF | BankG) => (TexturePalette, size, 0x4000 * ((ofs & 0b1) + 4 * ((ofs >> 1) & 0b1))),
+      // only the first 8K of bank I is used for palettes
+      (3, BankI) => (EngineBObjExtendedPalette, 0x2000, 0),
+      (4, BankC) => (EngineBBg, size, 0),
+      (4, BankD) => (EngineBObj, size, 0),
+      // only the first 32K of bank E is used for palettes
+      (4, BankE) => (EngineABgExtendedPalette, 0x8000, 0),
+      (4, BankF | BankG) => (EngineABgExtendedPalette, size, 0x4000 * (ofs & 0b1)),
+      (5, BankF | BankG) => (EngineAObjExtendedPalette, 0x2000, 0),
+      _ => return None
+    };
 
-          Self::add_mapping(&mut self.engine_a_obj, bank, size, offset);
-        }
-        Bank::BankC | Bank::BankD => {
-          let offset = vramcnt.vram_offset;
+    Some(mapping)
+  }
 
-          self.arm7_wram[offset as usize].insert(bank);
-        }
-        Bank::BankH => Self::add_mapping(&mut self.engine_b_bg_extended_palette, bank, size, 0),
-        Bank::BankI => Self::add_mapping(&mut self.engine_b_obj, bank, size, 0),
-      }
-      3 => match bank {
-        Bank::BankI => {
-          size = 0x2000;
+  fn check_texture_region(&mut self, region: VramRegion) {
+    if matches!(region, VramRegion::Textures | VramRegion::TexturePalette) {
+      self.texture_generation = self.texture_generation.wrapping_add(1);
+    }
+  }
 
-          Self::add_mapping(&mut self.engine_b_obj_extended_palette, bank, size, 0);
-        }
-        Bank::BankA | Bank::BankB | Bank::BankC | Bank::BankD => {
-          let offset = 128 * 1024 * vramcnt.vram_offset as usize;
+  fn get_region(&mut self, region: VramRegion) -> &mut Vec<HashSet<Bank>> {
+    match region {
+      VramRegion::EngineABg => &mut self.engine_a_bg,
+      VramRegion::EngineAObj => &mut self.engine_a_obj,
+      VramRegion::EngineBBg => &mut self.engine_b_bg,
+      VramRegion::EngineBObj => &mut self.engine_b_obj,
+      VramRegion::Arm7Wram => &mut self.arm7_wram,
+      VramRegion::EngineABgExtendedPalette => &mut self.engine_a_bg_extended_palette,
+      VramRegion::EngineBBgExtendedPalette => &mut self.engine_b_bg_extended_palette,
+      VramRegion::EngineAObjExtendedPalette => &mut self.engine_a_obj_extended_palette,
+      VramRegion::EngineBObjExtendedPalette => &mut self.engine_b_obj_extended_palette,
+      VramRegion::Textures => &mut self.textures,
+      VramRegion::TexturePalette => &mut self.texture_palette,
+      VramRegion::Lcdc => unreachable!("lcdc isn't split into blocks")
+    }
+  }
 
-          Self::add_mapping(&mut self.textures, bank, size, offset);
-        }
-        Bank::BankE => Self::add_mapping(&mut self.texture_palette, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          let index = (vramcnt.vram_offset & 0b1) + ((vramcnt.vram_offset >> 1) & 0b1) * 4;
-          let offset = 16 * 1204 * index as usize;
+  /// Banks can be mapped on top of each other, in which case reads return every bank ORed together
+  /// and writes go to all of them.
+  pub fn map_bank(&mut self, bank: Bank, vramcnt: &VramControlRegister) {
+    let Some((region, size, offset)) = Self::get_bank_mapping(bank, vramcnt.vram_mst, vramcnt.vram_offset) else {
+      return;
+    };
 
-          Self::add_mapping(&mut self.texture_palette, bank, size, offset);
-        }
-        _ => panic!("invalid bank given for mst = 3")
-      }
-      4 => match bank {
-        Bank::BankC => {
-          Self::add_mapping(&mut self.engine_b_bg, bank, size, 0);
-        }
-        Bank::BankD => {
-          Self::add_mapping(&mut self.engine_b_obj, bank, size, 0);
-        }
-        Bank::BankE => {
-          size = 0x8000;
+    self.check_texture_region(region);
 
-          Self::add_mapping(&mut self.engine_a_bg_extended_palette, bank, size, 0);
-        }
-        Bank::BankF | Bank::BankG => {
-          let offset = match vramcnt.vram_offset {
-            0 => 0,
-            1 => 16 * 1024,
-            _ => panic!("invalid offset given")
-          };
-
-          Self::add_mapping(&mut self.engine_a_bg_extended_palette, bank, size, offset);
-        }
-        _ => panic!("invalid option given for mst = 4")
+    match region {
+      VramRegion::Lcdc => {
+        self.lcdc.insert(bank);
       }
-      5 => match bank {
-        Bank::BankF | Bank::BankG => {
-          size = 0x2000;
-
-          Self::add_mapping(&mut self.engine_a_obj_extended_palette, bank, size, 0);
-        }
-        _ => panic!("invalid option given for mst = 5")
+      VramRegion::Arm7Wram => {
+        self.arm7_wram[offset / BANK_SIZES[BANK_C as usize]].insert(bank);
       }
-      _ => todo!("mst = {} not yet implemented", vramcnt.vram_mst)
+      _ => Self::add_mapping(self.get_region(region), bank, size, offset)
     }
   }
 
   pub fn unmap_bank(&mut self, bank: Bank, vramcnt: &VramControlRegister) {
-    let mut size = BANK_SIZES[bank as usize] as usize;
-    match vramcnt.vram_mst {
-      0 => {
+    let Some((region, size, offset)) = Self::get_bank_mapping(bank, vramcnt.vram_mst, vramcnt.vram_offset) else {
+      return;
+    };
+
+    self.check_texture_region(region);
+
+    match region {
+      VramRegion::Lcdc => {
         self.lcdc.remove(&bank);
       }
-      1 => match bank {
-        Bank::BankA | Bank::BankB | Bank::BankC | Bank::BankD => {
-          let offset = 0x2_0000 * vramcnt.vram_offset as usize;
-
-          Self::remove_mapping(&mut self.engine_a_bg, bank, size, offset);
-        }
-        Bank::BankE => Self::remove_mapping(&mut self.engine_a_bg, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          //(4000h*OFS.0)+(10000h*OFS.1)
-          let offset = 0x4000 * ((vramcnt.vram_offset as usize) & 0x1) + 0x10000 * (((vramcnt.vram_offset as usize) >> 1) & 0x1);
-          Self::remove_mapping(&mut self.engine_a_bg, bank, size, offset);
-        }
-        Bank::BankH => Self::remove_mapping(&mut self.engine_b_bg, bank, size, 0),
-        Bank::BankI => Self::remove_mapping(&mut self.engine_b_bg, bank, size, 0)
+      VramRegion::Arm7Wram => {
+        self.arm7_wram[offset / BANK_SIZES[BANK_C as usize]].remove(&bank);
       }
-      2 => match bank {
-        Bank::BankA | Bank::BankB  => {
-          let offset = 0x2_0000 * ((vramcnt.vram_offset as usize) & 0x1);
+      _ => Self::remove_mapping(self.get_region(region), bank, size, offset)
+    }
+  }
+}
 
-          Self::remove_mapping(&mut self.engine_a_obj, bank, size, offset);
-        }
-        Bank::BankE => Self::remove_mapping(&mut self.engine_a_obj, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          let offset = 0x4000 * (vramcnt.vram_offset as usize & 0x1) + 0x1000 * (((vramcnt.vram_offset as usize) >> 1) & 0x1);
+#[cfg(test)]
+mod tests {
+  use crate::gpu::registers::vram_control_register::VramControlRegister;
+
+  use super::{Bank, VRam, VramRegion};
+
+  use Bank::*;
+  use VramRegion::*;
+
+  /// Every valid bank and MST, with the size of the mapping and its offset for each value of OFS.
+  const MAPPINGS: [(Bank, u8, VramRegion, usize, [usize; 4]); 42] = [
+    (BankA, 0, Lcdc, 0x2_0000, [0; 4]),
+    (BankB, 0, Lcdc, 0x2_0000, [0; 4]),
+    (BankC, 0, Lcdc, 0x2_0000, [0; 4]),
+    (BankD, 0, Lcdc, 0x2_0000, [0; 4]),
+    (BankE, 0, Lcdc, 0x1_0000, [0; 4]),
+    (BankF, 0, Lcdc, 0x4000, [0; 4]),
+    (BankG, 0, Lcdc, 0x4000, [0; 4]),
+    (BankH, 0, Lcdc, 0x8000, [0; 4]),
+    (BankI, 0, Lcdc, 0x4000, [0; 4]),
+    (BankA, 1, EngineABg, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankB, 1, EngineABg, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankC, 1, EngineABg, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankD, 1, EngineABg, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankE, 1, EngineABg, 0x1_0000, [0; 4]),
+    (BankF, 1, EngineABg, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankG, 1, EngineABg, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankH, 1, EngineBBg, 0x8000, [0; 4]),
+    (BankI, 1, EngineBBg, 0x4000, [0x8000; 4]),
+    (BankA, 2, EngineAObj, 0x2_0000, [0, 0x2_0000, 0, 0x2_0000]),
+    (BankB, 2, EngineAObj, 0x2_0000, [0, 0x2_0000, 0, 0x2_0000]),
+    (BankC, 2, Arm7Wram, 0x2_0000, [0, 0x2_0000, 0, 0x2_0000]),
+    (BankD, 2, Arm7Wram, 0x2_0000, [0, 0x2_0000, 0, 0x2_0000]),
+    (BankE, 2, EngineAObj, 0x1_0000, [0; 4]),
+    (BankF, 2, EngineAObj, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankG, 2, EngineAObj, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankH, 2, EngineBBgExtendedPalette, 0x8000, [0; 4]),
+    (BankI, 2, EngineBObj, 0x4000, [0; 4]),
+    (BankA, 3, Textures, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankB, 3, Textures, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankC, 3, Textures, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankD, 3, Textures, 0x2_0000, [0, 0x2_0000, 0x4_0000, 0x6_0000]),
+    (BankE, 3, TexturePalette, 0x1_0000, [0; 4]),
+    (BankF, 3, TexturePalette, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankG, 3, TexturePalette, 0x4000, [0, 0x4000, 0x1_0000, 0x1_4000]),
+    (BankI, 3, EngineBObjExtendedPalette, 0x2000, [0; 4]),
+    (BankC, 4, EngineBBg, 0x2_0000, [0; 4]),
+    (BankD, 4, EngineBObj, 0x2_0000, [0; 4]),
+    (BankE, 4, EngineABgExtendedPalette, 0x8000, [0; 4]),
+    (BankF, 4, EngineABgExtendedPalette, 0x4000, [0, 0x4000, 0, 0x4000]),
+    (BankG, 4, EngineABgExtendedPalette, 0x4000, [0, 0x4000, 0, 0x4000]),
+    (BankF, 5, EngineAObjExtendedPalette, 0x2000, [0; 4]),
+    (BankG, 5, EngineAObjExtendedPalette, 0x2000, [0; 4])
+  ];
+
+  fn vramcnt(bank: Bank, mst: u8, ofs: u8) -> VramControlRegister {
+    let mut vramcnt = VramControlRegister::new(bank as usize);
+
+    vramcnt.write(0x80 | ofs << 3 | mst);
+
+    vramcnt
+  }
 
-          Self::remove_mapping(&mut self.engine_a_obj, bank, size, offset);
+  #[test]
+  fn banks_map_where_gbatek_says() {
+    for bank in (0..9).map(Bank::new) {
+      for mst in 0..8 {
+        for ofs in 0..4 {
+          let expected = MAPPINGS
+            .iter()
+            .find(|mapping| mapping.0 == bank && mapping.1 == mst)
+            .map(|&(_, _, region, size, offsets)| (region, size, offsets[ofs as usize]));
+
+          assert_eq!(VRam::get_bank_mapping(bank, mst, ofs), expected, "bank {bank:?}, mst {mst}, ofs {ofs}");
         }
-        Bank::BankC | Bank::BankD => {
-          let offset = vramcnt.vram_offset as usize;
+      }
+    }
+  }
 
-          self.arm7_wram[offset].remove(&bank);
-        }
-        Bank::BankH => Self::remove_mapping(&mut self.engine_b_bg_extended_palette, bank, size, 0),
-        Bank::BankI => Self::remove_mapping(&mut self.engine_b_obj, bank, size, 0),
+  #[test]
+  fn invalid_settings_map_nothing() {
+    let invalid = [
+      (BankA, 5),
+      (BankA, 6),
+      (BankA, 7),
+      (BankB, 4),
+      (BankE, 5),
+      (BankH, 3),
+      (BankH, 4),
+      (BankI, 4),
+      (BankC, 5),
+      (BankF, 6)
+    ];
+
+    for (bank, mst) in invalid {
+      for ofs in 0..4 {
+        assert_eq!(VRam::get_bank_mapping(bank, mst, ofs), None, "bank {bank:?}, mst {mst}, ofs {ofs}");
       }
-      3 => match bank {
-        Bank::BankI => {
-          size = 0x2000;
+    }
+  }
 
-          Self::remove_mapping(&mut self.engine_b_obj_extended_palette, bank, size, 0);
-        }
-        Bank::BankA | Bank::BankB | Bank::BankC | Bank::BankD => {
-          let offset = 128 * 1024 * vramcnt.vram_offset as usize;
+  #[test]
+  fn banks_mapped_to_the_same_block_are_ored_together() {
+    let mut vram = VRam::new();
 
-          Self::remove_mapping(&mut self.textures, bank, size, offset);
-        }
-        Bank::BankE => Self::remove_mapping(&mut self.texture_palette, bank, size, 0),
-        Bank::BankF | Bank::BankG => {
-          let index = (vramcnt.vram_offset & 0b1) + ((vramcnt.vram_offset >> 1) & 0b1) * 4;
-          let offset = 16 * 1204 * index as usize;
+    vram.map_bank(BankA, &vramcnt(BankA, 0, 0));
+    vram.map_bank(BankB, &vramcnt(BankB, 0, 0));
 
-          Self::remove_mapping(&mut self.texture_palette, bank, size, offset);
-        }
-        _ => panic!("invalid bank given for mst = 3")
-      }
-      4 => match bank {
-        Bank::BankC => {
-          Self::remove_mapping(&mut self.engine_b_bg, bank, size, 0);
-        }
-        Bank::BankD => {
-          Self::remove_mapping(&mut self.engine_b_obj, bank, size, 0);
-        }
-        Bank::BankE => {
-          size = 0x8000;
+    vram.write_lcdc_bank(BankA, 0x10, 0x0fu8);
+    vram.write_lcdc_bank(BankB, 0x10, 0xf0u8);
 
-          Self::remove_mapping(&mut self.engine_a_bg_extended_palette, bank, size, 0);
-        }
-        Bank::BankF | Bank::BankG => {
-          let offset = match vramcnt.vram_offset {
-            0 => 0,
-            1 => 16 * 1024,
-            _ => panic!("invalid offset given")
-          };
-
-          Self::remove_mapping(&mut self.engine_a_bg_extended_palette, bank, size, offset);
-        }
-        _ => panic!("invalid option given for mst = 4")
-      }
-      5 => match bank {
-        Bank::BankF | Bank::BankG => {
-          size = 0x2000;
+    vram.unmap_bank(BankA, &vramcnt(BankA, 0, 0));
+    vram.unmap_bank(BankB, &vramcnt(BankB, 0, 0));
 
-          Self::remove_mapping(&mut self.engine_a_obj_extended_palette, bank, size, 0);
-        }
-        _ => panic!("invalid option given for mst = 5")
-      }
-      _ => todo!("unimplemented")
-    };
+    vram.map_bank(BankA, &vramcnt(BankA, 1, 0));
+    vram.map_bank(BankB, &vramcnt(BankB, 1, 0));
+
+    assert_eq!(vram.read_engine_a_bg::<u8>(0x10), 0xff);
+
+    // writes go to every bank in the block
+    vram.write_engine_a_bg(0x20, 0x1234u16);
+
+    assert_eq!(&vram.banks[BankA as usize][0x20..0x22], &[0x34, 0x12]);
+    assert_eq!(&vram.banks[BankB as usize][0x20..0x22], &[0x34, 0x12]);
+
+    // and once one of them is mapped elsewhere only the other one is left
+    vram.unmap_bank(BankB, &vramcnt(BankB, 1, 0));
+    vram.map_bank(BankB, &vramcnt(BankB, 1, 1));
+
+    assert_eq!(vram.read_engine_a_bg::<u8>(0x10), 0x0f);
+    assert_eq!(vram.read_engine_a_bg::<u8>(0x2_0010), 0xf0);
   }
-}
\ No newline at end of file
+}
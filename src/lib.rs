@@ -7,4 +7,6 @@ pub mod util;
 pub mod gpu;
 pub mod scheduler;
 pub mod apu;
-pub mod number;
\ No newline at end of file
+pub mod number;
+pub mod video;
+pub mod settings;
\ No newline at end of file
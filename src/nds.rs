@@ -10,9 +10,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
   cpu::{
     bus::{cartridge::Header, Bus},
+    registers::external_key_input_register::ExternalKeyInputRegister,
     CPU
   },
-  scheduler::EventType
+  gpu::SCREEN_HEIGHT,
+  scheduler::EventType,
+  settings::Settings
 };
 
 #[derive(Serialize, Deserialize)]
@@ -118,6 +121,16 @@ impl Nds {
     bus.scheduler.load_save_state();
   }
 
+  pub fn apply_settings(&mut self, settings: &Settings) {
+    let ref mut bus = *self.bus.borrow_mut();
+
+    bus.gpu.engine3d.unlimited_polygons = settings.unlimited_polygons;
+    bus.gpu.engine3d.set_threaded_rendering(settings.threaded_rendering);
+    bus.gpu.engine3d.scanline_rendering = settings.scanline_rendering;
+    bus.gpu.set_resolution_scale(settings.resolution_scale);
+    bus.arm7.apu.set_interpolation(settings.interpolation);
+  }
+
   pub fn reset(&mut self, rom: &Vec<u8>) {
     {
       let ref mut bus = *self.bus.borrow_mut();
@@ -170,7 +183,15 @@ impl Nds {
 
       match event_type {
         EventType::HBlank => bus.gpu.handle_hblank(&mut bus.scheduler, &mut interrupt_requests, &mut dma_channels, cycles_left),
-        EventType::HDraw => bus.gpu.start_next_line(&mut bus.scheduler, &mut interrupt_requests, &mut dma_channels, cycles_left),
+        EventType::HDraw => {
+          bus.gpu.start_next_line(&mut bus.scheduler, &mut interrupt_requests, &mut dma_channels, cycles_left);
+
+          if bus.gpu.vcount == SCREEN_HEIGHT {
+            let pen_down = !bus.arm7.extkeyin.contains(ExternalKeyInputRegister::PEN_DOWN);
+
+            bus.gpu.update_main_screen_hint(pen_down);
+          }
+        }
         EventType::Timer7(timer_id) => {
           let timers = &mut bus.arm7.timers;
 
@@ -0,0 +1,33 @@
+use crate::apu::interpolation::Interpolation;
+
+/*
+  Enhancements and options chosen by the frontend. They aren't part of the emulated state,
+  and resets and save states replace the whole bus, so frontends apply them again every
+  frame with `Nds::apply_settings`.
+*/
+#[derive(Copy, Clone)]
+pub struct Settings {
+  pub unlimited_polygons: bool,
+  pub threaded_rendering: bool,
+  pub scanline_rendering: bool,
+  pub resolution_scale: usize,
+  pub interpolation: Interpolation
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Settings {
+  pub fn new() -> Self {
+    Self {
+      unlimited_polygons: false,
+      threaded_rendering: false,
+      scanline_rendering: false,
+      resolution_scale: 1,
+      interpolation: Interpolation::None
+    }
+  }
+}
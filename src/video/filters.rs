@@ -0,0 +1,202 @@
+use color_correction::{ColorCorrection, ColorCorrector};
+use overlay::Overlay;
+
+use crate::gpu::{registers::power_control_register1::PowerControlRegister1, GPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub mod color_correction;
+pub mod hqx;
+pub mod overlay;
+pub mod scale2x;
+pub mod xbrz;
+
+pub const MAX_UPSCALE_FACTOR: usize = 4;
+
+/*
+  Post processing for the finished images of both screens, done on the CPU so every frontend
+  can share it. The chain runs in this order: ghosting, color correction, upscaling, then the
+  LCD overlay.
+
+  Images are RGBA8 like the 2D engines' pixel buffers, and can already be larger than 256x192
+  when the internal resolution is raised.
+*/
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Upscaler {
+  None,
+  Scale2x,
+  Hqx,
+  Xbrz
+}
+
+impl Upscaler {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => Upscaler::Scale2x,
+      2 => Upscaler::Hqx,
+      3 => Upscaler::Xbrz,
+      _ => Upscaler::None
+    }
+  }
+}
+
+pub struct VideoFilters {
+  pub upscaler: Upscaler,
+  pub upscale_factor: usize,
+  pub overlay: Overlay,
+  pub color_correction: ColorCorrection,
+  /// Blends every frame with the one before it, like the slow LCDs games were made for.
+  /// Some games flicker objects on and off every other frame to make them look transparent.
+  pub ghosting: bool,
+  previous_frames: [Vec<u8>; 2],
+  outputs: [Vec<u8>; 2],
+  color_corrector: ColorCorrector
+}
+
+impl VideoFilters {
+  pub fn new() -> Self {
+    Self {
+      upscaler: Upscaler::None,
+      upscale_factor: 2,
+      overlay: Overlay::None,
+      color_correction: ColorCorrection::None,
+      ghosting: false,
+      previous_frames: [Vec::new(), Vec::new()],
+      outputs: [Vec::new(), Vec::new()],
+      color_corrector: ColorCorrector::new()
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.upscaler != Upscaler::None ||
+      self.overlay != Overlay::None ||
+      self.color_correction != ColorCorrection::None ||
+      self.ghosting
+  }
+
+  /// Runs the image of one screen (0 for the top screen, 1 for the bottom) through the chain.
+  /// Returns the width and height of the result, which can be read with `output`.
+  pub fn apply(&mut self, screen: usize, image: &[u8], width: usize, height: usize) -> (usize, usize) {
+    let mut image = image.to_vec();
+
+    if self.ghosting {
+      let previous_frame = &mut self.previous_frames[screen];
+
+      if previous_frame.len() == image.len() {
+        for (value, previous) in image.iter_mut().zip(previous_frame.iter_mut()) {
+          let current = *value;
+
+          *value = (current as u32 + *previous as u32).div_ceil(2) as u8;
+          *previous = current;
+        }
+      } else {
+        *previous_frame = image.clone();
+      }
+    } else {
+      self.previous_frames[screen].clear();
+    }
+
+    self.color_corrector.apply(&mut image, self.color_correction);
+
+    let factor = self.upscale_factor.clamp(2, MAX_UPSCALE_FACTOR);
+
+    let (mut image, mut width, mut height) = match self.upscaler {
+      Upscaler::None => (image, width, height),
+      Upscaler::Scale2x => (scale2x::upscale(&image, width, height, factor), width * factor, height * factor),
+      Upscaler::Hqx => (hqx::upscale(&image, width, height, factor), width * factor, height * factor),
+      Upscaler::Xbrz => (xbrz::upscale(&image, width, height, factor), width * factor, height * factor)
+    };
+
+    if self.overlay != Overlay::None {
+      // the overlays need at least two output pixels for every pixel of the DS screen
+      if width < 2 * SCREEN_WIDTH as usize {
+        image = upscale_nearest(&image, width, height, 2);
+        width *= 2;
+        height *= 2;
+      }
+
+      overlay::apply(&mut image, width, height, width / SCREEN_WIDTH as usize, self.overlay);
+    }
+
+    self.outputs[screen] = image;
+
+    (width, height)
+  }
+
+  /// Filters the images of both screens, in the order they're shown. Returns the size of each.
+  pub fn apply_to_screens(&mut self, gpu: &GPU) -> (usize, usize) {
+    let (top, bottom) = if gpu.powcnt1.contains(PowerControlRegister1::TOP_A) {
+      (&gpu.engine_a.pixels, &gpu.engine_b.pixels)
+    } else {
+      (&gpu.engine_b.pixels, &gpu.engine_a.pixels)
+    };
+
+    let scale = gpu.resolution_scale();
+
+    let (width, height) = (SCREEN_WIDTH as usize * scale, SCREEN_HEIGHT as usize * scale);
+
+    self.apply(0, top, width, height);
+    self.apply(1, bottom, width, height)
+  }
+
+  /// The filtered image of the top (0) or bottom (1) screen, anything past the bottom screen is clamped to it.
+  pub fn output(&self, screen: usize) -> &[u8] {
+    &self.outputs[screen.min(1)]
+  }
+}
+
+impl Default for VideoFilters {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn upscale_nearest(image: &[u8], width: usize, height: usize, factor: usize) -> Vec<u8> {
+  let pixels = to_pixels(image);
+
+  let mut output = vec![0; pixels.len() * factor * factor];
+
+  for y in 0..height * factor {
+    for x in 0..width * factor {
+      output[x + y * width * factor] = pixels[x / factor + (y / factor) * width];
+    }
+  }
+
+  to_image(&output)
+}
+
+/// Packs every RGBA8 pixel into a u32, which makes comparing pixels a lot simpler.
+fn to_pixels(image: &[u8]) -> Vec<u32> {
+  image
+    .chunks_exact(4)
+    .map(|pixel| u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+    .collect()
+}
+
+fn to_image(pixels: &[u32]) -> Vec<u8> {
+  pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect()
+}
+
+/// Returns the pixel at (x, y), with coordinates outside of the image clamped to its edges.
+fn get_pixel(pixels: &[u32], width: usize, height: usize, x: isize, y: isize) -> u32 {
+  let x = x.clamp(0, width as isize - 1) as usize;
+  let y = y.clamp(0, height as isize - 1) as usize;
+
+  pixels[x + y * width]
+}
+
+/// Weighted average of the pixels, done separately for each channel.
+fn mix(pixels: &[(u32, u32)]) -> u32 {
+  let total: u32 = pixels.iter().map(|(_, weight)| weight).sum();
+
+  let mut result = 0;
+
+  for shift in [0, 8, 16, 24] {
+    let sum: u32 = pixels
+      .iter()
+      .map(|(pixel, weight)| ((pixel >> shift) & 0xff) * weight)
+      .sum();
+
+    result |= ((sum + total / 2) / total) << shift;
+  }
+
+  result
+}
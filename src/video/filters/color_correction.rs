@@ -0,0 +1,89 @@
+/// The DS LCDs have a higher gamma than most monitors, which makes their colors look darker.
+const LCD_GAMMA: f32 = 2.4;
+const DISPLAY_GAMMA: f32 = 2.2;
+
+/// Approximates the washed out colors of the DS LCD: each output channel (rows) mixes in some
+/// of the other channels, and the whole screen is slightly dimmer.
+const LCD_PROFILE: [[f32; 3]; 3] = [
+  [0.705, 0.235, 0.06],
+  [0.09, 0.585, 0.325],
+  [0.1075, 0.1725, 0.72]
+];
+const LCD_LUMINANCE: f32 = 0.93;
+
+/// Linear values get looked up with this much precision when converting back to the display's gamma.
+const LINEAR_STEPS: usize = 4096;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ColorCorrection {
+  None,
+  /// Only the gamma of the LCD.
+  Gamma,
+  /// Gamma along with the colors of the LCD.
+  Lcd
+}
+
+impl ColorCorrection {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => ColorCorrection::Gamma,
+      2 => ColorCorrection::Lcd,
+      _ => ColorCorrection::None
+    }
+  }
+}
+
+/// Color correction using lookup tables for the gamma curves, since calling `powf` for every
+/// channel of every pixel would be a lot slower.
+pub struct ColorCorrector {
+  to_linear: [f32; 256],
+  to_display: Box<[u8]>
+}
+
+impl ColorCorrector {
+  pub fn new() -> Self {
+    let mut to_linear = [0.0; 256];
+
+    for (i, value) in to_linear.iter_mut().enumerate() {
+      *value = (i as f32 / 255.0).powf(LCD_GAMMA);
+    }
+
+    let to_display = (0..LINEAR_STEPS)
+      .map(|i| ((i as f32 / (LINEAR_STEPS - 1) as f32).powf(1.0 / DISPLAY_GAMMA) * 255.0).round() as u8)
+      .collect();
+
+    Self {
+      to_linear,
+      to_display
+    }
+  }
+
+  pub fn apply(&self, image: &mut [u8], mode: ColorCorrection) {
+    if mode == ColorCorrection::None {
+      return;
+    }
+
+    let encode = |value: f32| self.to_display[(value.clamp(0.0, 1.0) * (LINEAR_STEPS - 1) as f32).round() as usize];
+
+    for pixel in image.chunks_exact_mut(4) {
+      let linear = [self.to_linear[pixel[0] as usize], self.to_linear[pixel[1] as usize], self.to_linear[pixel[2] as usize]];
+
+      for channel in 0..3 {
+        pixel[channel] = match mode {
+          ColorCorrection::Lcd => {
+            let row = LCD_PROFILE[channel];
+
+            encode((row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]) * LCD_LUMINANCE)
+          }
+          _ => encode(linear[channel])
+        };
+      }
+    }
+  }
+}
+
+impl Default for ColorCorrector {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,96 @@
+use super::{get_pixel, mix, to_image, to_pixels};
+
+/*
+  An HQx style upscaler. Neighboring pixels are compared using the same YUV thresholds as
+  hq2x, and the corners of each pixel get blended with the neighbors that continue an edge
+  through them. Instead of hq2x's lookup tables, every corner is worked out from its two
+  adjacent neighbors and the diagonal one, which works the same way at 2x, 3x and 4x.
+*/
+pub fn upscale(image: &[u8], width: usize, height: usize, factor: usize) -> Vec<u8> {
+  let pixels = to_pixels(image);
+
+  let output_width = width * factor;
+
+  let mut output = vec![0; pixels.len() * factor * factor];
+
+  for y in 0..height {
+    for x in 0..width {
+      let pixel = |dx: isize, dy: isize| get_pixel(&pixels, width, height, x as isize + dx, y as isize + dy);
+
+      let center = pixel(0, 0);
+
+      // the color each side of the pixel blends towards, output pixels on the middle row or
+      // column only have one neighbor on their side
+      let mut corners = [center; 9];
+
+      for dy in -1..=1 {
+        for dx in -1..=1 {
+          let horizontal = if dx != 0 { pixel(dx, 0) } else { center };
+          let vertical = if dy != 0 { pixel(0, dy) } else { center };
+          let diagonal = match (dx, dy) {
+            (0, _) => vertical,
+            (_, 0) => horizontal,
+            _ => pixel(dx, dy)
+          };
+
+          corners[(dx + 1 + (dy + 1) * 3) as usize] = get_corner_color(center, horizontal, vertical, diagonal);
+        }
+      }
+
+      for sub_y in 0..factor {
+        for sub_x in 0..factor {
+          // which side of the pixel's center the output pixel is on, in halves of an output pixel
+          let offset_x = 2 * sub_x as isize + 1 - factor as isize;
+          let offset_y = 2 * sub_y as isize + 1 - factor as isize;
+
+          let corner = corners[(offset_x.signum() + 1 + (offset_y.signum() + 1) * 3) as usize];
+
+          // the further out towards the corner, the more the corner color takes over
+          let distance = (offset_x.abs() + offset_y.abs()) as u32;
+          let max_distance = 2 * (factor as u32 - 1);
+
+          output[x * factor + sub_x + (y * factor + sub_y) * output_width] = if corner == center || distance == max_distance {
+            corner
+          } else {
+            mix(&[(corner, distance), (center, max_distance - distance)])
+          };
+        }
+      }
+    }
+  }
+
+  to_image(&output)
+}
+
+fn get_corner_color(center: u32, horizontal: u32, vertical: u32, diagonal: u32) -> u32 {
+  if center == horizontal && center == vertical && center == diagonal {
+    center
+  } else if !is_different(horizontal, vertical) && is_different(center, horizontal) {
+    // an edge runs through the corner
+    if is_different(center, diagonal) {
+      mix(&[(center, 2), (horizontal, 1), (vertical, 1)])
+    } else {
+      // the pixel is part of a thin diagonal line, which shouldn't get cut through
+      mix(&[(center, 6), (horizontal, 1), (vertical, 1)])
+    }
+  } else if is_different(center, diagonal) {
+    mix(&[(center, 3), (diagonal, 1)])
+  } else {
+    center
+  }
+}
+
+fn is_different(pixel1: u32, pixel2: u32) -> bool {
+  let (y1, u1, v1) = to_yuv(pixel1);
+  let (y2, u2, v2) = to_yuv(pixel2);
+
+  y1.abs_diff(y2) > 0x30 || u1.abs_diff(u2) > 0x7 || v1.abs_diff(v2) > 0x6
+}
+
+fn to_yuv(pixel: u32) -> (i32, i32, i32) {
+  let r = (pixel & 0xff) as i32;
+  let g = ((pixel >> 8) & 0xff) as i32;
+  let b = ((pixel >> 16) & 0xff) as i32;
+
+  ((r + g + b) >> 2, 128 + ((r - b) >> 2), 128 + ((2 * g - r - b) >> 3))
+}
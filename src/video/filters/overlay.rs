@@ -0,0 +1,51 @@
+/// How much of a pixel's brightness is kept where the overlay darkens it, out of 256.
+const GRID_BRIGHTNESS: u32 = 176;
+const SCANLINE_BRIGHTNESS: u32 = 144;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Overlay {
+  None,
+  /// Dark lines between every pixel of the DS screen, like the gaps between the LCD's cells.
+  LcdGrid,
+  /// A dark line below every row of pixels.
+  Scanlines
+}
+
+impl Overlay {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => Overlay::LcdGrid,
+      2 => Overlay::Scanlines,
+      _ => Overlay::None
+    }
+  }
+}
+
+/// Darkens the image along the edges of every DS pixel, which covers `cell_size` pixels of
+/// the image in each direction.
+pub fn apply(image: &mut [u8], width: usize, height: usize, cell_size: usize, overlay: Overlay) {
+  if cell_size < 2 {
+    return;
+  }
+
+  // wider lines look better once there's room for them
+  let line_width = (cell_size / 4).max(1);
+
+  let is_line = |position: usize| position % cell_size >= cell_size - line_width;
+
+  for y in 0..height {
+    for x in 0..width {
+      let brightness = match overlay {
+        Overlay::LcdGrid if is_line(x) || is_line(y) => GRID_BRIGHTNESS,
+        Overlay::Scanlines if is_line(y) => SCANLINE_BRIGHTNESS,
+        _ => continue
+      };
+
+      let index = 4 * (x + y * width);
+
+      for value in &mut image[index..index + 3] {
+        *value = ((*value as u32 * brightness) >> 8) as u8;
+      }
+    }
+  }
+}
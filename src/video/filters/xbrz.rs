@@ -0,0 +1,272 @@
+use super::{get_pixel, mix, to_image, to_pixels, MAX_UPSCALE_FACTOR};
+
+const EQUAL_COLOR_TOLERANCE: f32 = 30.0;
+const DOMINANT_DIRECTION_THRESHOLD: f32 = 3.6;
+const STEEP_DIRECTION_THRESHOLD: f32 = 2.2;
+
+const BLEND_NONE: u8 = 0;
+const BLEND_NORMAL: u8 = 1;
+const BLEND_DOMINANT: u8 = 2;
+
+/*
+  xBRZ, which finds edges by comparing the color gradients across both diagonals of every
+  2x2 block of pixels, then rounds off the corners they run through. Edges can be blended
+  as shallow, steep or 45 degree lines, or just have the corner cut off.
+
+  Each pixel keeps the blend type of its four corners in a byte, two bits each, going
+  clockwise from the top left. The corners are handled one at a time by rotating the
+  neighborhood so the corner being blended is always the bottom right one.
+*/
+pub fn upscale(image: &[u8], width: usize, height: usize, factor: usize) -> Vec<u8> {
+  let pixels = to_pixels(image);
+
+  let blend_info = get_blend_info(&pixels, width, height);
+
+  let output_width = width * factor;
+
+  let mut output = vec![0; pixels.len() * factor * factor];
+
+  for y in 0..height {
+    for x in 0..width {
+      let pixel = |dx: isize, dy: isize| get_pixel(&pixels, width, height, x as isize + dx, y as isize + dy);
+
+      let mut block = [pixel(0, 0); MAX_UPSCALE_FACTOR * MAX_UPSCALE_FACTOR];
+      let block = &mut block[..factor * factor];
+
+      let info = blend_info[x + y * width];
+
+      if info != BLEND_NONE {
+        let kernel = [
+          [pixel(-1, -1), pixel(0, -1), pixel(1, -1)],
+          [pixel(-1, 0), pixel(0, 0), pixel(1, 0)],
+          [pixel(-1, 1), pixel(0, 1), pixel(1, 1)]
+        ];
+
+        for rotation in 0..4 {
+          blend_corner(&rotate_kernel(&kernel, rotation), rotate_blend_info(info, rotation), block, factor, rotation);
+        }
+      }
+
+      for sub_y in 0..factor {
+        let start = x * factor + (y * factor + sub_y) * output_width;
+
+        output[start..start + factor].copy_from_slice(&block[sub_y * factor..(sub_y + 1) * factor]);
+      }
+    }
+  }
+
+  to_image(&output)
+}
+
+/// Finds which corners of each pixel are along an edge, and how strongly.
+fn get_blend_info(pixels: &[u32], width: usize, height: usize) -> Vec<u8> {
+  let mut blend_info = vec![BLEND_NONE; pixels.len()];
+
+  // every 2x2 block touching the image is checked, including the ones hanging over its edges
+  for y in -1..height as isize {
+    for x in -1..width as isize {
+      let pixel = |dx: isize, dy: isize| get_pixel(pixels, width, height, x + dx, y + dy);
+
+      // a b c d
+      // e f g h
+      // i j k l
+      // m n o p
+      let kernel = [
+        [pixel(-1, -1), pixel(0, -1), pixel(1, -1), pixel(2, -1)],
+        [pixel(-1, 0), pixel(0, 0), pixel(1, 0), pixel(2, 0)],
+        [pixel(-1, 1), pixel(0, 1), pixel(1, 1), pixel(2, 1)],
+        [pixel(-1, 2), pixel(0, 2), pixel(1, 2), pixel(2, 2)]
+      ];
+
+      let (blend_f, blend_g, blend_j, blend_k) = get_block_blend(&kernel);
+
+      let mut set = |px: isize, py: isize, value: u8, shift: u8| {
+        if px >= 0 && py >= 0 && px < width as isize && py < height as isize {
+          blend_info[px as usize + py as usize * width] |= value << shift;
+        }
+      };
+
+      set(x, y, blend_f, 4);
+      set(x + 1, y, blend_g, 6);
+      set(x, y + 1, blend_j, 2);
+      set(x + 1, y + 1, blend_k, 0);
+    }
+  }
+
+  blend_info
+}
+
+/// Returns how the inner corners of the 2x2 block in the middle of the kernel get blended.
+fn get_block_blend(kernel: &[[u32; 4]; 4]) -> (u8, u8, u8, u8) {
+  let [[_, b, c, _], [e, f, g, h], [i, j, k, l], [_, n, o, _]] = *kernel;
+
+  let mut result = (BLEND_NONE, BLEND_NONE, BLEND_NONE, BLEND_NONE);
+
+  // flat and checkered blocks don't have any edges
+  if (f == g && j == k) || (f == j && g == k) {
+    return result;
+  }
+
+  let jg = distance(i, f) + distance(f, c) + distance(n, k) + distance(k, h) + 4.0 * distance(j, g);
+  let fk = distance(e, j) + distance(j, o) + distance(b, g) + distance(g, l) + 4.0 * distance(f, k);
+
+  if jg < fk {
+    // the edge runs along the j-g diagonal
+    let blend = if DOMINANT_DIRECTION_THRESHOLD * jg < fk { BLEND_DOMINANT } else { BLEND_NORMAL };
+
+    if f != g && f != j {
+      result.0 = blend;
+    }
+    if k != j && k != g {
+      result.3 = blend;
+    }
+  } else if fk < jg {
+    // the edge runs along the f-k diagonal
+    let blend = if DOMINANT_DIRECTION_THRESHOLD * fk < jg { BLEND_DOMINANT } else { BLEND_NORMAL };
+
+    if j != f && j != k {
+      result.2 = blend;
+    }
+    if g != f && g != k {
+      result.1 = blend;
+    }
+  }
+
+  result
+}
+
+/// Blends the bottom right corner of the block, which has been rotated by `rotation` quarter turns.
+fn blend_corner(kernel: &[[u32; 3]; 3], info: u8, block: &mut [u32], factor: usize, rotation: usize) {
+  let [[_, b, c], [d, e, f], [g, h, i]] = *kernel;
+
+  let (top_right, bottom_right, bottom_left) = ((info >> 2) & 0x3, (info >> 4) & 0x3, (info >> 6) & 0x3);
+
+  if bottom_right == BLEND_NONE {
+    return;
+  }
+
+  let is_equal = |pixel1: u32, pixel2: u32| distance(pixel1, pixel2) < EQUAL_COLOR_TOLERANCE;
+
+  let do_line_blend = if bottom_right >= BLEND_DOMINANT {
+    true
+  } else if top_right != BLEND_NONE && !is_equal(e, g) {
+    // another corner of the pixel is already being blended, so just this one gets cut off
+    false
+  } else if bottom_left != BLEND_NONE && !is_equal(e, c) {
+    false
+  } else {
+    // L shapes only get their corner blended
+    !(!is_equal(e, i) && is_equal(g, h) && is_equal(h, i) && is_equal(i, f) && is_equal(f, c))
+  };
+
+  let color = if distance(e, f) <= distance(e, h) { f } else { h };
+
+  let shape = if do_line_blend {
+    let fg = distance(f, g);
+    let hc = distance(h, c);
+
+    let is_shallow = STEEP_DIRECTION_THRESHOLD * fg <= hc && e != g && d != g;
+    let is_steep = STEEP_DIRECTION_THRESHOLD * hc <= fg && e != c && b != c;
+
+    match (is_shallow, is_steep) {
+      (true, true) => BlendShape::SteepAndShallow,
+      (true, false) => BlendShape::Shallow,
+      (false, true) => BlendShape::Steep,
+      (false, false) => BlendShape::Diagonal
+    }
+  } else {
+    BlendShape::Corner
+  };
+
+  for &(row, column, amount, total) in get_blend_pattern(shape, factor) {
+    // undo the rotation to find where the output pixel actually is
+    let (mut row, mut column) = (row, column);
+
+    for _ in 0..rotation {
+      (row, column) = (factor - 1 - column, row);
+    }
+
+    let pixel = &mut block[column + row * factor];
+
+    *pixel = mix(&[(color, amount), (*pixel, total - amount)]);
+  }
+}
+
+#[derive(Copy, Clone)]
+enum BlendShape {
+  Corner,
+  Diagonal,
+  Shallow,
+  Steep,
+  SteepAndShallow
+}
+
+/// The output pixels of the bottom right corner that get blended for each shape, and by how
+/// much, as (row, column, amount, total).
+fn get_blend_pattern(shape: BlendShape, factor: usize) -> &'static [(usize, usize, u32, u32)] {
+  match (factor, shape) {
+    (2, BlendShape::Corner) => &[(1, 1, 21, 100)],
+    (2, BlendShape::Diagonal) => &[(1, 1, 1, 2)],
+    (2, BlendShape::Shallow) => &[(1, 0, 1, 4), (1, 1, 3, 4)],
+    (2, BlendShape::Steep) => &[(0, 1, 1, 4), (1, 1, 3, 4)],
+    (2, BlendShape::SteepAndShallow) => &[(1, 0, 1, 4), (0, 1, 1, 4), (1, 1, 5, 6)],
+    (3, BlendShape::Corner) => &[(2, 2, 45, 100)],
+    (3, BlendShape::Diagonal) => &[(1, 2, 1, 8), (2, 1, 1, 8), (2, 2, 7, 8)],
+    (3, BlendShape::Shallow) => &[(2, 0, 1, 4), (1, 2, 1, 4), (2, 1, 3, 4), (2, 2, 1, 1)],
+    (3, BlendShape::Steep) => &[(0, 2, 1, 4), (2, 1, 1, 4), (1, 2, 3, 4), (2, 2, 1, 1)],
+    (3, BlendShape::SteepAndShallow) => &[(2, 0, 1, 4), (0, 2, 1, 4), (2, 1, 3, 4), (1, 2, 3, 4), (2, 2, 1, 1)],
+    (_, BlendShape::Corner) => &[(3, 3, 68, 100), (3, 2, 9, 100), (2, 3, 9, 100)],
+    (_, BlendShape::Diagonal) => &[(3, 2, 1, 2), (2, 3, 1, 2), (3, 3, 1, 1)],
+    (_, BlendShape::Shallow) => &[(3, 0, 1, 4), (2, 2, 1, 4), (3, 1, 3, 4), (2, 3, 3, 4), (3, 2, 1, 1), (3, 3, 1, 1)],
+    (_, BlendShape::Steep) => &[(0, 3, 1, 4), (2, 2, 1, 4), (1, 3, 3, 4), (3, 2, 3, 4), (2, 3, 1, 1), (3, 3, 1, 1)],
+    (_, BlendShape::SteepAndShallow) => &[
+      (3, 1, 3, 4),
+      (1, 3, 3, 4),
+      (3, 0, 1, 4),
+      (0, 3, 1, 4),
+      (2, 2, 1, 3),
+      (3, 3, 1, 1),
+      (3, 2, 1, 1),
+      (2, 3, 1, 1)
+    ]
+  }
+}
+
+/// Rotates the neighborhood clockwise by `rotation` quarter turns, so the corner that was at
+/// the top right ends up at the bottom right after one turn.
+fn rotate_kernel(kernel: &[[u32; 3]; 3], rotation: usize) -> [[u32; 3]; 3] {
+  let mut rotated = *kernel;
+
+  for _ in 0..rotation {
+    let previous = rotated;
+
+    for row in 0..3 {
+      for column in 0..3 {
+        rotated[row][column] = previous[2 - column][row];
+      }
+    }
+  }
+
+  rotated
+}
+
+fn rotate_blend_info(info: u8, rotation: usize) -> u8 {
+  info.rotate_left(2 * rotation as u32)
+}
+
+/// Distance between the colors in YCbCr space, which is closer to how different they look.
+fn distance(pixel1: u32, pixel2: u32) -> f32 {
+  let channel = |shift: u32| ((pixel1 >> shift) & 0xff) as f32 - ((pixel2 >> shift) & 0xff) as f32;
+
+  let (r, g, b) = (channel(0), channel(8), channel(16));
+
+  const K_B: f32 = 0.0593;
+  const K_R: f32 = 0.2627;
+  const K_G: f32 = 1.0 - K_B - K_R;
+
+  let y = K_R * r + K_G * g + K_B * b;
+  let c_b = 0.5 / (1.0 - K_B) * (b - y);
+  let c_r = 0.5 / (1.0 - K_R) * (r - y);
+
+  (y * y + c_b * c_b + c_r * c_r).sqrt()
+}
@@ -0,0 +1,236 @@
+use crate::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/*
+  Works out where both screens go in a window. The screens are first laid out at 1x in DS
+  pixels, then the whole layout gets rotated, scaled to fit the window and centered.
+
+  For the hybrid and single screen layouts, the main screen is the top one unless the screens
+  are swapped. The other layouts just show them in the opposite order.
+*/
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LayoutKind {
+  Vertical,
+  Horizontal,
+  Hybrid,
+  Single
+}
+
+impl LayoutKind {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => LayoutKind::Horizontal,
+      2 => LayoutKind::Hybrid,
+      3 => LayoutKind::Single,
+      _ => LayoutKind::Vertical
+    }
+  }
+}
+
+/// Clockwise rotation of the whole layout. Games like Brain Age are played with the DS held sideways.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Rotation {
+  None,
+  Rotate90,
+  Rotate180,
+  Rotate270
+}
+
+impl Rotation {
+  pub fn from(value: u8) -> Self {
+    match value {
+      1 => Rotation::Rotate90,
+      2 => Rotation::Rotate180,
+      3 => Rotation::Rotate270,
+      _ => Rotation::None
+    }
+  }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ScreenRect {
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32
+}
+
+impl ScreenRect {
+  fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+    Self { x, y, width, height }
+  }
+
+  pub fn contains(&self, x: f32, y: f32) -> bool {
+    x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+  }
+}
+
+pub struct ScreenLayout {
+  pub kind: LayoutKind,
+  pub rotation: Rotation,
+  pub swap_screens: bool,
+  /// Only scales the screens by whole numbers, so every DS pixel is the same size.
+  pub integer_scale: bool,
+  /// Space between the screens, in DS pixels.
+  pub gap: u32
+}
+
+impl ScreenLayout {
+  pub fn new() -> Self {
+    Self {
+      kind: LayoutKind::Vertical,
+      rotation: Rotation::None,
+      swap_screens: false,
+      integer_scale: false,
+      gap: 0
+    }
+  }
+
+  /// Size of the layout at 1x, after it's been rotated.
+  pub fn size(&self) -> (u32, u32) {
+    let (_, width, height) = self.unrotated_rects();
+
+    let (width, height) = (width as u32, height as u32);
+
+    if self.is_sideways() {
+      (height, width)
+    } else {
+      (width, height)
+    }
+  }
+
+  /// Where the top and bottom screens should be drawn in a window of the given size. Screens
+  /// that aren't shown are None. Rotated screens should be drawn with `rotate_image`.
+  pub fn screen_rects(&self, window_width: u32, window_height: u32) -> [Option<ScreenRect>; 2] {
+    let (rects, width, height) = self.unrotated_rects();
+
+    let rects = rects.map(|rect| rect.map(|rect| self.rotate_rect(rect, width, height)));
+
+    let (width, height) = if self.is_sideways() { (height, width) } else { (width, height) };
+
+    let mut scale = (window_width as f32 / width).min(window_height as f32 / height);
+
+    if self.integer_scale {
+      scale = scale.floor().max(1.0);
+    }
+
+    let offset_x = (window_width as f32 - width * scale) / 2.0;
+    let offset_y = (window_height as f32 - height * scale) / 2.0;
+
+    rects.map(|rect| rect.map(|rect| ScreenRect::new(
+      offset_x + rect.x * scale,
+      offset_y + rect.y * scale,
+      rect.width * scale,
+      rect.height * scale
+    )))
+  }
+
+  /// Maps a point in the window back to touch screen coordinates. Returns None if the
+  /// point isn't on the bottom screen.
+  pub fn map_touch(&self, window_width: u32, window_height: u32, x: i32, y: i32) -> Option<(u16, u16)> {
+    let rect = self.screen_rects(window_width, window_height)[1]?;
+
+    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+
+    if !rect.contains(x, y) {
+      return None;
+    }
+
+    let rotated_x = (x - rect.x) / rect.width;
+    let rotated_y = (y - rect.y) / rect.height;
+
+    // undo the rotation, in coordinates relative to the screen's size
+    let (screen_x, screen_y) = match self.rotation {
+      Rotation::None => (rotated_x, rotated_y),
+      Rotation::Rotate90 => (rotated_y, 1.0 - rotated_x),
+      Rotation::Rotate180 => (1.0 - rotated_x, 1.0 - rotated_y),
+      Rotation::Rotate270 => (1.0 - rotated_y, rotated_x)
+    };
+
+    let screen_x = (screen_x * SCREEN_WIDTH as f32) as u16;
+    let screen_y = (screen_y * SCREEN_HEIGHT as f32) as u16;
+
+    Some((screen_x.min(SCREEN_WIDTH - 1), screen_y.min(SCREEN_HEIGHT - 1)))
+  }
+
+  /// Rotates an RGBA8 screen image to match the layout. Returns the image and its new width and height.
+  pub fn rotate_image(&self, image: &[u8], width: usize, height: usize) -> (Vec<u8>, usize, usize) {
+    let (rotated_width, rotated_height) = if self.is_sideways() { (height, width) } else { (width, height) };
+
+    let mut rotated = vec![0; rotated_width * rotated_height * 4];
+
+    for y in 0..height {
+      for x in 0..width {
+        let (rotated_x, rotated_y) = match self.rotation {
+          Rotation::None => (x, y),
+          Rotation::Rotate90 => (height - 1 - y, x),
+          Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+          Rotation::Rotate270 => (y, width - 1 - x)
+        };
+
+        let index = 4 * (x + y * width);
+        let rotated_index = 4 * (rotated_x + rotated_y * rotated_width);
+
+        rotated[rotated_index..rotated_index + 4].copy_from_slice(&image[index..index + 4]);
+      }
+    }
+
+    (rotated, rotated_width, rotated_height)
+  }
+
+  fn is_sideways(&self) -> bool {
+    matches!(self.rotation, Rotation::Rotate90 | Rotation::Rotate270)
+  }
+
+  /// The rects of the top and bottom screens at 1x before rotation, and the size of the whole layout.
+  fn unrotated_rects(&self) -> ([Option<ScreenRect>; 2], f32, f32) {
+    let (width, height) = (SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    let gap = self.gap as f32;
+
+    let (main, other, layout_width, layout_height) = match self.kind {
+      LayoutKind::Vertical => (
+        Some(ScreenRect::new(0.0, 0.0, width, height)),
+        Some(ScreenRect::new(0.0, height + gap, width, height)),
+        width,
+        height * 2.0 + gap
+      ),
+      LayoutKind::Horizontal => (
+        Some(ScreenRect::new(0.0, 0.0, width, height)),
+        Some(ScreenRect::new(width + gap, 0.0, width, height)),
+        width * 2.0 + gap,
+        height
+      ),
+      // the main screen at twice the size, with the other one next to it
+      LayoutKind::Hybrid => (
+        Some(ScreenRect::new(0.0, 0.0, width * 2.0, height * 2.0)),
+        Some(ScreenRect::new(width * 2.0 + gap, height / 2.0, width, height)),
+        width * 3.0 + gap,
+        height * 2.0
+      ),
+      LayoutKind::Single => (
+        Some(ScreenRect::new(0.0, 0.0, width, height)),
+        None,
+        width,
+        height
+      )
+    };
+
+    let rects = if self.swap_screens { [other, main] } else { [main, other] };
+
+    (rects, layout_width, layout_height)
+  }
+
+  fn rotate_rect(&self, rect: ScreenRect, layout_width: f32, layout_height: f32) -> ScreenRect {
+    match self.rotation {
+      Rotation::None => rect,
+      Rotation::Rotate90 => ScreenRect::new(layout_height - rect.y - rect.height, rect.x, rect.height, rect.width),
+      Rotation::Rotate180 => ScreenRect::new(layout_width - rect.x - rect.width, layout_height - rect.y - rect.height, rect.width, rect.height),
+      Rotation::Rotate270 => ScreenRect::new(rect.y, layout_width - rect.x - rect.width, rect.height, rect.width)
+    }
+  }
+}
+
+impl Default for ScreenLayout {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -2,13 +2,15 @@ extern crate ds_emulator;
 extern crate console_error_panic_hook;
 
 use ds_emulator::{
-  apu::Sample,
+  apu::{interpolation::Interpolation, Sample},
   cpu::{
     bus::{backup_file::BackupFile, cartridge::BackupType, spi::SPI, touchscreen::SAMPLE_SIZE},
     registers::{external_key_input_register::ExternalKeyInputRegister, key_input_register::KeyInputRegister}
   },
-  gpu::registers::power_control_register1::PowerControlRegister1,
-  nds::Nds
+  gpu::{main_screen::Screen, registers::power_control_register1::PowerControlRegister1},
+  nds::Nds,
+  settings::Settings,
+  video::filters::{color_correction::ColorCorrection, overlay::Overlay, Upscaler, VideoFilters}
 };
 use wasm_bindgen::prelude::*;
 use std::{
@@ -54,7 +56,10 @@ pub struct WasmEmulator {
   nds: Nds,
   key_map: HashMap<ButtonEvent, KeyInputRegister>,
   extkey_map: HashMap<ButtonEvent, ExternalKeyInputRegister>,
-  state_len: usize
+  state_len: usize,
+  settings: Settings,
+  video_filters: VideoFilters,
+  filtered_size: (usize, usize)
 }
 
 #[wasm_bindgen]
@@ -106,7 +111,10 @@ impl WasmEmulator {
       ),
       key_map,
       extkey_map,
-      state_len: 0
+      state_len: 0,
+      settings: Settings::new(),
+      video_filters: VideoFilters::new(),
+      filtered_size: (0, 0)
     };
 
     emu.nds.init(&game_data.to_vec(), true);
@@ -297,6 +305,74 @@ impl WasmEmulator {
     self.state_len
   }
 
+  pub fn set_unlimited_polygons(&mut self, val: bool) {
+    self.settings.unlimited_polygons = val;
+  }
+
+  pub fn set_scanline_rendering(&mut self, val: bool) {
+    self.settings.scanline_rendering = val;
+  }
+
+  /// 0 = none, 1 = linear, 2 = cosine, 3 = cubic, 4 = gaussian
+  pub fn set_interpolation(&mut self, val: u8) {
+    self.settings.interpolation = Interpolation::from(val);
+  }
+
+  pub fn set_resolution_scale(&mut self, val: usize) {
+    self.settings.resolution_scale = val;
+  }
+
+  /// The picture pointers point to images this many times larger than 256x192 in each direction.
+  pub fn resolution_scale(&self) -> usize {
+    self.nds.bus.borrow().gpu.resolution_scale()
+  }
+
+  /// 0 = none, 1 = Scale2x, 2 = HQx, 3 = xBRZ
+  pub fn set_upscaler(&mut self, val: u8) {
+    self.video_filters.upscaler = Upscaler::from(val);
+  }
+
+  pub fn set_upscale_factor(&mut self, val: usize) {
+    self.video_filters.upscale_factor = val;
+  }
+
+  /// 0 = none, 1 = LCD grid, 2 = scanlines
+  pub fn set_overlay(&mut self, val: u8) {
+    self.video_filters.overlay = Overlay::from(val);
+  }
+
+  /// 0 = none, 1 = LCD gamma, 2 = LCD gamma and colors
+  pub fn set_color_correction(&mut self, val: u8) {
+    self.video_filters.color_correction = ColorCorrection::from(val);
+  }
+
+  pub fn set_ghosting(&mut self, val: bool) {
+    self.video_filters.ghosting = val;
+  }
+
+  /// When the filters are enabled, the filtered pictures should be shown instead of the engines' pictures.
+  pub fn filters_enabled(&self) -> bool {
+    self.video_filters.is_enabled()
+  }
+
+  /// Screen 0 is the top screen and 1 is the bottom one, they're already swapped according to POWCNT1.
+  pub fn get_filtered_picture_pointer(&self, screen: usize) -> *const u8 {
+    self.video_filters.output(screen).as_ptr()
+  }
+
+  pub fn filtered_width(&self) -> usize {
+    self.filtered_size.0
+  }
+
+  pub fn filtered_height(&self) -> usize {
+    self.filtered_size.1
+  }
+
+  /// Whether the bottom screen is the one that should be enlarged or shown in hybrid and single screen layouts.
+  pub fn is_bottom_main_screen(&self) -> bool {
+    self.nds.bus.borrow().gpu.main_screen_hint.screen == Screen::Bottom
+  }
+
   pub fn set_pause(&mut self, val: bool) {
     if val {
       self.nds.paused = true;
@@ -335,6 +411,8 @@ impl WasmEmulator {
 
     let start_cycles = self.nds.arm7_cpu.cycles;
 
+    self.nds.apply_settings(&self.settings);
+
     while !(frame_finished) {
       if !self.nds.paused {
         frame_finished = self.nds.step();
@@ -352,6 +430,10 @@ impl WasmEmulator {
       self.nds.arm7_cpu.cycles -= to_subtract;
     }
 
+    if self.video_filters.is_enabled() {
+      self.filtered_size = self.video_filters.apply_to_screens(&bus.gpu);
+    }
+
     bus.gpu.frame_finished = false;
   }
 }
\ No newline at end of file